@@ -1,60 +1,66 @@
-use std::{collections::HashMap, thread};
+use std::{collections::HashMap, path::Path, thread};
 
 use engine::{
-    board::{Board, ParsingBoardErr},
+    board::Board,
     engine::Engine,
-    opening_book::OpeningBook,
+    opening_book::{OpeningBook, WEAK_BOOK_KEY},
 };
 
 const SCORE_SHIFT: u8 = 127;
-const BOOK_PATH: &'static str = "opening-book-test";
+const BOOK_PATH: &str = "opening-book-test";
+/*
+    with --weak the book only holds 1, 0 or -1 for a win, a draw or a loss,
+    and is faster to generate, engines only read it in their weak searches
+*/
+const WEAK_BOOK_PATH: &str = "opening-book-weak-test";
+// shared by all the workers
+const TABLE_MEMORY_MB: usize = 1024;
+// the table of the previous run, if any, warm starts the next one
+const TABLE_PATH: &str = "transposition-table";
 
 fn generate_subtree(
     engine: &mut Engine,
-    encoded_board: &mut String,
+    board: Board,
     depth: usize,
+    weak: bool,
     map: &mut HashMap<u64, i32>,
 ) {
-    let board: Result<Board, ParsingBoardErr> = encoded_board.parse();
-    if let Ok(board) = board {
-        let key = board.key();
+    // mirrored positions share their entry
+    let key = board.canonical_key();
 
-        if map.contains_key(&key) {
-            return;
-        }
+    if map.contains_key(&key) {
+        return;
+    }
 
-        let score = engine.score(board);
-        map.insert(key, score);
+    let score = if weak {
+        engine.weak_score(board)
+    } else {
+        engine.score(board)
+    };
+    map.insert(key, score);
 
-        if depth == 0 {
-            return;
-        }
+    if depth == 0 {
+        return;
+    }
 
-        for col in 1..=7 {
-            encoded_board.push(char::from_digit(col as u32, 10).unwrap());
-            generate_subtree(engine, encoded_board, depth - 1, map);
-            encoded_board.pop();
+    for (col, child) in board.children() {
+        // the game is over after a winning move
+        if !board.is_winning(col) {
+            generate_subtree(engine, child, depth - 1, weak, map);
         }
     }
 }
 
-fn generate_book_parallel(depth: usize, engine: Engine) -> HashMap<u64, i32> {
+fn generate_book_parallel(depth: usize, weak: bool, engine: Engine) -> HashMap<u64, i32> {
     thread::scope(|s| {
         let mut handlers = Vec::new();
 
-        for col in 1..=7 {
+        for (_, board) in Board::new().children() {
+            // the clones share the transposition table of engine
             let mut engine = engine.clone();
             let handle = s.spawn(move || {
                 let mut map = HashMap::new();
-                let mut encoded_board = col.to_string();
-
-                generate_subtree(
-                    &mut engine,
-                    &mut encoded_board,
-                    depth - 1,
-                    &mut map,
-                );
-
+                generate_subtree(&mut engine, board, depth - 1, weak, &mut map);
                 map
             });
 
@@ -74,13 +80,21 @@ fn generate_book_parallel(depth: usize, engine: Engine) -> HashMap<u64, i32> {
     })
 }
 
-pub fn save_book(book: &HashMap<u64, i32>, book_path: &str) {
+pub fn save_book(book: &HashMap<u64, i32>, book_path: &str, weak: bool) {
     use std::fs::File;
     use std::io::Write;
 
     let file = File::create(book_path).unwrap();
     let mut writer = std::io::BufWriter::new(file);
 
+    // so that engines do not read the signs of the scores as exact scores
+    if weak {
+        writer
+            .write_all(&WEAK_BOOK_KEY.to_le_bytes()[0..7])
+            .unwrap();
+        writer.write_all(&[SCORE_SHIFT]).unwrap();
+    }
+
     for (&key, &score) in book {
         let encoded_value = (score + SCORE_SHIFT as i32) as u8;
 
@@ -93,7 +107,15 @@ pub fn save_book(book: &HashMap<u64, i32>, book_path: &str) {
 }
 
 fn main() {
-    let engine = Engine::with_book(OpeningBook::open("./opening-book-8").unwrap());
-    let book = generate_book_parallel(8, engine);
-    save_book(&book, BOOK_PATH);
+    let mut engine = Engine::builder()
+        .table_memory_mb(TABLE_MEMORY_MB)
+        .book(OpeningBook::open("./opening-book-8").unwrap())
+        .build();
+    if Path::new(TABLE_PATH).exists() {
+        engine.load_table(TABLE_PATH).unwrap();
+    }
+    let weak = std::env::args().any(|arg| arg == "--weak");
+    let book = generate_book_parallel(8, weak, engine.clone());
+    save_book(&book, if weak { WEAK_BOOK_PATH } else { BOOK_PATH }, weak);
+    engine.save_table(TABLE_PATH).unwrap();
 }
@@ -1,82 +1,231 @@
-use std::{
-    str::FromStr,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use engine::{
-    board::{Board, WIDTH},
-    engine::Engine,
+    board::{GameStatus, WIDTH},
+    engine::{CancellationToken, Engine},
+    game::GameRecord,
     opening_book::OpeningBook,
+    outcome::Outcome,
+    progress::SearchProgress,
 };
-use tauri::State;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+// the default 64 MB table is more than needed next to an opening book
+const TABLE_MEMORY_MB: usize = 16;
+
+fn new_engine(book: OpeningBook) -> Engine {
+    Engine::builder()
+        .table_memory_mb(TABLE_MEMORY_MB)
+        .book(book)
+        .build()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusView {
+    status: &'static str,
+    winning_cells: Vec<(usize, usize)>,
+}
+
+// moves is the number of moves of the winner, 0 for a draw or when only the outcome is known
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutcomeView {
+    outcome: &'static str,
+    moves: usize,
+}
+
+impl From<Outcome> for OutcomeView {
+    fn from(outcome: Outcome) -> Self {
+        let (outcome, moves) = match outcome {
+            Outcome::Win(moves) => ("win", moves),
+            Outcome::Draw => ("draw", 0),
+            Outcome::Loss(moves) => ("loss", moves),
+        };
+        Self { outcome, moves }
+    }
+}
+
+impl OutcomeView {
+    // from a score of Engine::weak_solve_scores
+    fn from_weak_score(score: i32) -> Self {
+        let outcome = match score.signum() {
+            1 => "win",
+            0 => "draw",
+            _ => "loss",
+        };
+        Self { outcome, moves: 0 }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressView {
+    nodes: usize,
+    nodes_per_second: f64,
+    elapsed_ms: u64,
+    finished: bool,
+}
+
+impl From<&SearchProgress> for ProgressView {
+    fn from(progress: &SearchProgress) -> Self {
+        Self {
+            nodes: progress.nodes,
+            nodes_per_second: progress.nodes_per_second,
+            elapsed_ms: progress.elapsed.as_millis() as u64,
+            finished: progress.finished,
+        }
+    }
+}
 
-struct Game {
-    engine: Engine,
-    encoded_board: String,
+/*
+    columns_score holds the engine for the whole search, the record and the token
+    of the running search live in their own mutexes so that the other commands
+    can read the game or stop the search instead of waiting for it
+*/
+struct App {
+    record: Mutex<GameRecord>,
+    engine: Mutex<Engine>,
+    search: Mutex<CancellationToken>,
 }
 
-impl Game {
-    fn new() -> Self {
+impl Default for App {
+    fn default() -> Self {
         Self {
-            engine: Engine::new(),
-            encoded_board: String::new(),
+            record: Mutex::new(GameRecord::new()),
+            engine: Mutex::new(new_engine(OpeningBook::new())),
+            search: Mutex::new(CancellationToken::new()),
         }
     }
 }
 
-type AppState<'a> = State<'a, Arc<Mutex<Game>>>;
+impl App {
+    fn cancel_search(&self) {
+        self.search.lock().unwrap().cancel();
+    }
+
+    // the running search is stopped first, otherwise the engine is held until it ends
+    fn lock_engine(&self) -> MutexGuard<'_, Engine> {
+        self.cancel_search();
+        self.engine.lock().unwrap()
+    }
+
+    // for the commands which change the game, the running search scores the previous board
+    fn lock_record(&self) -> MutexGuard<'_, GameRecord> {
+        self.cancel_search();
+        self.record.lock().unwrap()
+    }
+}
+
+type AppState<'a> = State<'a, App>;
 
 #[tauri::command]
 fn get_encoded_board(state: AppState) -> String {
-    let game = state.lock().unwrap();
-    game.encoded_board.clone()
+    let record = state.record.lock().unwrap();
+    record.to_string()
 }
 
 #[tauri::command]
 fn open_book(book_path: String, state: AppState) -> Result<(), String> {
-    let mut game = state.lock().unwrap();
+    let mut engine = state.lock_engine();
     let book = OpeningBook::open(&book_path).map_err(|err| err.to_string())?;
-    game.engine = Engine::with_book(book);
+    *engine = new_engine(book);
     Ok(())
 }
 
+#[tauri::command]
+fn save_table(table_path: String, state: AppState) -> Result<(), String> {
+    let engine = state.lock_engine();
+    engine
+        .save_table(&table_path)
+        .map_err(|err| err.to_string())
+}
+
+// the table keeps the results of a previous session
+#[tauri::command]
+fn load_table(table_path: String, state: AppState) -> Result<(), String> {
+    let mut engine = state.lock_engine();
+    engine
+        .load_table(&table_path)
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 fn play_colm(colm: usize, state: AppState) -> Result<(), String> {
-    let mut game = state.lock().unwrap();
+    // columns start from 1 in the frontend
+    let colm = colm
+        .checked_sub(1)
+        .ok_or_else(|| format!("Column {colm} is outside of the board"))?;
+    let mut record = state.lock_record();
+    record.play(colm).map_err(|err| err.to_string())
+}
 
-    let colm = char::from_digit(colm as u32, 10).ok_or("Invalid column digit")?;
-    game.encoded_board.push(colm);
+#[tauri::command]
+fn game_status(state: AppState) -> StatusView {
+    let board = state.record.lock().unwrap().board();
 
-    match Board::from_str(&game.encoded_board) {
-        Ok(_) => Ok(()),
-        Err(err) => {
-            game.encoded_board.pop();
-            Err(err.to_string())
-        }
+    let status = match board.status() {
+        GameStatus::Ongoing => "ongoing",
+        GameStatus::FirstPlayerWon => "firstPlayerWon",
+        GameStatus::SecondPlayerWon => "secondPlayerWon",
+        GameStatus::Draw => "draw",
+    };
+
+    StatusView {
+        status,
+        winning_cells: board.winning_line(),
     }
 }
 
 #[tauri::command]
 fn back_move(state: AppState) {
-    let mut game = state.lock().unwrap();
-    if !game.encoded_board.is_empty() {
-        game.encoded_board.pop();
-    }
+    state.lock_record().undo();
 }
 
 #[tauri::command]
-fn reset_game(state: AppState) {
-    let mut game = state.lock().unwrap();
-    game.engine.reset();
-    game.encoded_board.clear();
+fn forward_move(state: AppState) {
+    state.lock_record().redo();
 }
 
 #[tauri::command]
-fn columns_score(state: AppState) -> [Option<i32>; WIDTH] {
-    let mut game = state.lock().unwrap();
-    let board = game.encoded_board.parse().unwrap();
-    game.engine.solve(board)
+fn reset_game(state: AppState) {
+    state.lock_engine().reset();
+    *state.lock_record() = GameRecord::new();
+}
+
+/*
+    async so that it does not block the main thread which runs the other commands,
+    weak only tells wins, draws and losses apart, which is faster in the opening,
+    None when another command cancelled the search
+*/
+#[tauri::command(async)]
+fn columns_score(
+    weak: bool,
+    app: AppHandle,
+    state: AppState,
+) -> Option<[Option<OutcomeView>; WIDTH]> {
+    let mut engine = state.lock_engine();
+    // the token is replaced before reading the board, so a move played after the read cancels it
+    let cancel = CancellationToken::new();
+    *state.search.lock().unwrap() = cancel.clone();
+    let board = state.record.lock().unwrap().board();
+    if board.is_game_over() {
+        return Some([const { None }; WIDTH]);
+    }
+
+    // the frontend shows the progress as a thinking indicator
+    engine.set_observer(Some(Arc::new(move |progress: &SearchProgress| {
+        let _ = app.emit("search-progress", ProgressView::from(progress));
+    })));
+
+    if weak {
+        let scores = engine.try_weak_solve_scores(board, &cancel).ok()?;
+        return Some(scores.map(|score| score.map(OutcomeView::from_weak_score)));
+    }
+    let outcomes = engine.try_solve(board, &cancel).ok()?;
+    Some(outcomes.map(|outcome| outcome.map(OutcomeView::from)))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -84,14 +233,18 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
-        .manage(Arc::new(Mutex::new(Game::new())))
+        .manage(App::default())
         .invoke_handler(tauri::generate_handler![
             get_encoded_board,
             open_book,
+            save_table,
+            load_table,
             play_colm,
             back_move,
+            forward_move,
             reset_game,
-            columns_score
+            columns_score,
+            game_status
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,4 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import React from "react";
 
 const WIDTH = 7;
@@ -20,23 +22,93 @@ function buildBoard(encodedBoard: string): number[][] {
   return board;
 }
 
+type GameStatus = {
+  status: "ongoing" | "firstPlayerWon" | "secondPlayerWon" | "draw";
+  winningCells: [number, number][];
+};
+
+const STATUS_TEXT = {
+  ongoing: "",
+  firstPlayerWon: "Red wins!",
+  secondPlayerWon: "Yellow wins!",
+  draw: "Draw!",
+};
+
+// moves is 0 when only the outcome is known
+type ColumnOutcome = {
+  outcome: "win" | "draw" | "loss";
+  moves: number;
+};
+
+type SearchProgress = {
+  nodes: number;
+  nodesPerSecond: number;
+  elapsedMs: number;
+  finished: boolean;
+};
+
+function outcomeText(outcome: ColumnOutcome | null) {
+  if (outcome === null) {
+    return "-";
+  }
+  switch (outcome.outcome) {
+    case "win":
+      return outcome.moves ? `Win in ${outcome.moves}` : "Win";
+    case "loss":
+      return outcome.moves ? `Loss in ${outcome.moves}` : "Loss";
+    case "draw":
+      return "Draw";
+  }
+}
+
 function Game() {
-  const [scores, setScores] = React.useState<(number | null)[]>(
+  const [scores, setScores] = React.useState<(ColumnOutcome | null)[]>(
     Array(WIDTH).fill(null),
   );
   const [encodedBoard, setEncodedBoard] = React.useState("");
+  const [status, setStatus] = React.useState<GameStatus>({
+    status: "ongoing",
+    winningCells: [],
+  });
+
+  const [progress, setProgress] = React.useState<SearchProgress | null>(null);
+  // only win, draw or loss, which is faster to compute
+  const [weak, setWeak] = React.useState(false);
 
   React.useEffect(() => {
     refresh();
+  }, [weak]);
+
+  React.useEffect(() => {
+    const unlisten = listen<SearchProgress>("search-progress", (event) =>
+      setProgress(event.payload.finished ? null : event.payload),
+    );
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
   }, []);
 
+  // scores of an earlier refresh are stale even if their search was not cancelled
+  const lastRefresh = React.useRef(0);
+
+  // the board is shown first, its scores once the search ends
   async function refresh() {
+    const refreshId = ++lastRefresh.current;
     try {
-      const scores = await invoke<(number | null)[]>("columns_score");
       const encodedBoard = await invoke<string>("get_encoded_board");
-      setScores(scores);
+      const status = await invoke<GameStatus>("game_status");
       setEncodedBoard(encodedBoard);
-      console.log(scores);
+      setStatus(status);
+      setScores(Array(WIDTH).fill(null));
+
+      // null when a later command cancelled the search, which refreshes again
+      const scores = await invoke<(ColumnOutcome | null)[] | null>(
+        "columns_score",
+        { weak },
+      );
+      if (scores !== null && refreshId === lastRefresh.current) {
+        setScores(scores);
+      }
     } catch (err) {
       console.error(err);
     }
@@ -44,6 +116,12 @@ function Game() {
 
   const board = buildBoard(encodedBoard);
 
+  function isWinningCell(r: number, c: number) {
+    return status.winningCells.some(
+      ([colm, row]) => colm === c && HEIGHT - 1 - row === r,
+    );
+  }
+
   async function playColm(colm: number) {
     try {
       await invoke("play_colm", { colm });
@@ -58,6 +136,26 @@ function Game() {
     await refresh();
   }
 
+  async function forwardMove() {
+    await invoke("forward_move");
+    await refresh();
+  }
+
+  async function saveTable() {
+    const tablePath = await save({});
+    if (tablePath) {
+      await invoke("save_table", { tablePath }).catch(alert);
+    }
+  }
+
+  async function loadTable() {
+    const tablePath = await open({ multiple: false });
+    if (tablePath) {
+      await invoke("load_table", { tablePath }).catch(alert);
+      await refresh();
+    }
+  }
+
   async function resetGame() {
     await invoke("reset_game");
     await refresh();
@@ -70,7 +168,7 @@ function Game() {
           row.map((cell, c) => (
             <div
               key={`${r}-${c}`}
-              className="w-24 h-24 bg-white rounded-full flex items-center justify-center cursor-pointer hover:scale-105 transition"
+              className={`w-24 h-24 bg-white rounded-full flex items-center justify-center cursor-pointer hover:scale-105 transition ${isWinningCell(r, c) ? "ring-4 ring-green-400" : ""}`}
               onClick={() => playColm(c + 1)}
             >
               {cell === 1 && (
@@ -86,12 +184,22 @@ function Game() {
 
       <div className="grid grid-cols-7 gap-2 mt-3 text-center">
         {scores.map((score, i) => (
-          <div key={i} className="text-2xl font-semibold w-24">
-            {score !== null ? score : "-"}
+          <div key={i} className="text-xl font-semibold w-24">
+            {outcomeText(score)}
           </div>
         ))}
       </div>
 
+      <div className="text-sm text-gray-400 mt-2 h-5">
+        {progress &&
+          `Thinking... ${progress.nodes.toLocaleString()} nodes, ` +
+            `${Math.round(progress.nodesPerSecond / 1000).toLocaleString()}k nodes/s`}
+      </div>
+
+      <div className="text-3xl font-bold mt-4">
+        {STATUS_TEXT[status.status]}
+      </div>
+
       <div className="flex gap-2">
         <button
           onClick={backMove}
@@ -99,12 +207,36 @@ function Game() {
         >
           Back
         </button>
+        <button
+          onClick={forwardMove}
+          className="mt-8 px-4 py-2 bg-gray-700 rounded-lg hover:bg-gray-600 transition"
+        >
+          Forward
+        </button>
         <button
           onClick={resetGame}
           className="mt-8 px-4 py-2 bg-gray-700 rounded-lg hover:bg-gray-600 transition"
         >
           Reset
         </button>
+        <button
+          onClick={saveTable}
+          className="mt-8 px-4 py-2 bg-gray-700 rounded-lg hover:bg-gray-600 transition"
+        >
+          Save cache
+        </button>
+        <button
+          onClick={loadTable}
+          className="mt-8 px-4 py-2 bg-gray-700 rounded-lg hover:bg-gray-600 transition"
+        >
+          Load cache
+        </button>
+        <button
+          onClick={() => setWeak(!weak)}
+          className="mt-8 px-4 py-2 bg-gray-700 rounded-lg hover:bg-gray-600 transition"
+        >
+          {weak ? "Exact scores" : "Outcome only"}
+        </button>
       </div>
     </div>
   );
@@ -0,0 +1,86 @@
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    ops::{
+        Add, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Mul, Not, Shl,
+        ShlAssign, Shr, ShrAssign, Sub,
+    },
+};
+
+/*
+    the integer type which stores one bit per cell of the board,
+    u64 fits boards with (HEIGHT + 1) * WIDTH <= 64 and u128 everything up to 128
+*/
+pub trait Bitboard:
+    Copy
+    + Send
+    + Sync
+    + Eq
+    + Ord
+    + Hash
+    + Debug
+    + Default
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+    + BitAndAssign
+    + BitOrAssign
+    + BitXorAssign
+    + ShlAssign<usize>
+    + ShrAssign<usize>
+{
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+
+    // the masks are computed at compile time as u128 and truncated
+    fn from_u128(value: u128) -> Self;
+
+    fn count_ones(self) -> u32;
+
+    // key % modulus, used to index the transposition table
+    fn reduce(self, modulus: u64) -> u64;
+
+    // the lowest 64 bits
+    fn low_u64(self) -> u64;
+}
+
+macro_rules! impl_bitboard {
+    ($($ty:ty),*) => {
+        $(
+            impl Bitboard for $ty {
+                const BITS: usize = <$ty>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                #[inline]
+                fn from_u128(value: u128) -> Self {
+                    value as $ty
+                }
+
+                #[inline]
+                fn count_ones(self) -> u32 {
+                    <$ty>::count_ones(self)
+                }
+
+                #[inline]
+                fn reduce(self, modulus: u64) -> u64 {
+                    (self % modulus as $ty) as u64
+                }
+
+                #[inline]
+                fn low_u64(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_bitboard!(u64, u128);
@@ -1,22 +1,35 @@
 use std::{error::Error, fmt::Display, str::FromStr};
 
+use crate::{bitboard::Bitboard, outcome::Outcome};
+
 pub const WIDTH: usize = 7;
 pub const HEIGHT: usize = 6;
-
+// number of aligned stones needed to win
+pub const CONNECT: usize = 4;
 
 // compile time function to help generate bottom_mask
-const fn bottom(width: usize, height: usize) -> u64 {
+const fn bottom(width: usize, height: usize) -> u128 {
     if width == 0 {
         0
     } else {
-        bottom(width - 1, height) | (1u64 << ((width - 1) * (height + 1)))
+        bottom(width - 1, height) | (1u128 << ((width - 1) * (height + 1)))
     }
 }
 
-// one on the bottom of each column
-const BOTTOM_MASK: u64 = bottom(WIDTH, HEIGHT);
-// one everywhere except the the overflow row
-const BOARD_MASK: u64 = BOTTOM_MASK * ((1 << HEIGHT) - 1);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Empty,
+    FirstPlayer,
+    SecondPlayer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameStatus {
+    Ongoing,
+    FirstPlayerWon,
+    SecondPlayerWon,
+    Draw,
+}
 
 /*
     the board is encoded in this direction
@@ -34,70 +47,378 @@ col 0  1  2
     mask is a bitboard with 1 where there is a non-empty cell
     current is a bitboard where the cells of the current player are marked as 1
 */
-#[derive(Clone, Copy)]
-pub struct Board {
-    current: u64,
-    mask: u64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Board<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    current: B,
+    mask: B,
     played_moves: usize,
 }
 
-impl Board {
-    pub(crate) fn new() -> Self {
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default for Board<W, H, B, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameStatus {
+    fn opposite(self) -> Self {
+        match self {
+            GameStatus::FirstPlayerWon => GameStatus::SecondPlayerWon,
+            GameStatus::SecondPlayerWon => GameStatus::FirstPlayerWon,
+            status => status,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Board<W, H, B, K> {
+    // every column has an extra overflow cell on top
+    pub(crate) const FITS_IN_BITBOARD: () = assert!(
+        W * (H + 1) <= B::BITS,
+        "the board does not fit in the bitboard"
+    );
+    pub(crate) const VALID_CONNECT: () =
+        assert!(K >= 2, "at least two aligned stones are needed to win");
+    // one on the bottom of each column
+    const BOTTOM_MASK: u128 = bottom(W, H);
+    // one everywhere except the the overflow row
+    const BOARD_MASK: u128 = Self::BOTTOM_MASK * ((1 << H) - 1);
+
+    // empty board, the first player is to move
+    pub fn new() -> Self {
+        let () = Self::FITS_IN_BITBOARD;
+        let () = Self::VALID_CONNECT;
         Self {
-            current: 0,
-            mask: 0,
+            current: B::ZERO,
+            mask: B::ZERO,
             played_moves: 0,
         }
     }
 
-    pub(crate) fn can_play(&self, colm: usize) -> bool {
-        (self.mask & top_mask_colm(colm)) == 0
+    #[inline]
+    pub(crate) fn bottom_mask() -> B {
+        B::from_u128(Self::BOTTOM_MASK)
     }
 
-    pub(crate) fn play(&mut self, colm: usize) {
-        self.current = self.mask ^ self.current;
-        self.mask = self.mask | (self.mask + bottom_mask_colm(colm));
+    #[inline]
+    pub(crate) fn board_mask() -> B {
+        B::from_u128(Self::BOARD_MASK)
+    }
+
+    // false for full columns and for columns outside of the board
+    pub fn can_play(&self, colm: usize) -> bool {
+        colm < W && (self.mask & top_mask_colm::<B, H>(colm)) == B::ZERO
+    }
+
+    // the caller must make sure that can_play(colm) holds
+    pub fn play(&mut self, colm: usize) {
+        self.current ^= self.mask;
+        self.mask |= self.mask + bottom_mask_colm::<B, H>(colm);
         self.played_moves += 1;
     }
 
-    pub(crate) fn is_winning(&self, colm: usize) -> bool {
-        (self.current_winning_moves() & self.possible() & column_mask(colm)) != 0
+    // takes back the last move, the caller must make sure that it was played in colm
+    pub fn unplay(&mut self, colm: usize) {
+        let stones = self.mask & column_mask::<B, H>(colm);
+        // the stones of a column are contiguous from the bottom
+        let top = (stones + bottom_mask_colm::<B, H>(colm)) >> 1;
+        self.mask ^= top;
+        self.current ^= self.mask;
+        self.played_moves -= 1;
+    }
+
+    // checked version of play
+    pub fn try_play(&mut self, colm: usize) -> Result<(), InvalidMoveErr> {
+        if colm >= W {
+            return Err(InvalidMoveErr::ColumnOutOfRange(colm));
+        }
+        if !self.can_play(colm) {
+            return Err(InvalidMoveErr::ColumnFull(colm));
+        }
+        self.play(colm);
+        Ok(())
+    }
+
+    // columns which are not full, from left to right
+    pub fn legal_columns(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..W).filter(|&colm| self.can_play(colm))
+    }
+
+    // every legal column with the board after playing it
+    pub fn children(&self) -> impl Iterator<Item = (usize, Self)> + '_ {
+        self.legal_columns().map(|colm| {
+            let mut child = *self;
+            child.play(colm);
+            (colm, child)
+        })
     }
 
-    pub(crate) fn played_moves(&self) -> usize {
+    // true if the current player wins by playing in colm
+    pub fn is_winning(&self, colm: usize) -> bool {
+        colm < W
+            && (self.current_winning_moves() & self.possible() & column_mask::<B, H>(colm))
+                != B::ZERO
+    }
+
+    // (current, mask), the stones of the player to move and all stones
+    pub fn bitboards(&self) -> (B, B) {
+        (self.current, self.mask)
+    }
+
+    pub fn played_moves(&self) -> usize {
         self.played_moves
     }
 
-    pub fn key(&self) -> u64 {
+    // reads a score of this position given by the engine, None if it cannot be one
+    pub fn outcome(&self, score: i32) -> Option<Outcome> {
+        Outcome::from_score(score, W * H - self.played_moves)
+    }
+
+    pub fn key(&self) -> B {
         self.current + self.mask
     }
 
-    fn possible(&self) -> u64 {
-        (self.mask + BOTTOM_MASK) & BOARD_MASK
+    // key of the position reflected left to right, each column keeps its bit above the stones
+    pub fn mirrored_key(&self) -> B {
+        let key = self.key();
+        let colm_bits = (B::ONE << (H + 1)) - B::ONE;
+        let mut mirrored = B::ZERO;
+        for colm in 0..W {
+            let column = (key >> (colm * (H + 1))) & colm_bits;
+            mirrored |= column << ((W - 1 - colm) * (H + 1));
+        }
+        mirrored
     }
 
-    fn current_winning_moves(&self) -> u64 {
-        compute_winning_position(self.current, self.mask)
+    // same key for a position and its mirror, they have the same score
+    pub fn canonical_key(&self) -> B {
+        self.key().min(self.mirrored_key())
     }
 
-    fn opponent_winning_moves(&self) -> u64 {
-        compute_winning_position(self.current ^ self.mask, self.mask)
+    fn possible(&self) -> B {
+        (self.mask + Self::bottom_mask()) & Self::board_mask()
     }
 
-    pub(crate) fn can_win_next(&self) -> bool {
-        self.current_winning_moves() & self.possible() != 0
+    fn current_winning_moves(&self) -> B {
+        compute_winning_position::<W, H, B, K>(self.current, self.mask)
+    }
+
+    fn opponent_winning_moves(&self) -> B {
+        compute_winning_position::<W, H, B, K>(self.current ^ self.mask, self.mask)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        // the player who made the last move is the only one who could have connected K
+        let last_player = self.current ^ self.mask;
+        if compute_alignment::<B, H, K>(last_player) != B::ZERO {
+            return self.last_player_won();
+        }
+        // only reachable when the game was continued after a win
+        if compute_alignment::<B, H, K>(self.current) != B::ZERO {
+            return self.last_player_won().opposite();
+        }
+        if self.played_moves == W * H {
+            GameStatus::Draw
+        } else {
+            GameStatus::Ongoing
+        }
     }
 
-    pub(crate) fn possible_non_losing_moves(&self) -> u64{
+    pub fn is_game_over(&self) -> bool {
+        self.status() != GameStatus::Ongoing
+    }
+
+    // (column, row) of every cell which is part of a winning line, row 0 is the bottom
+    pub fn winning_line(&self) -> Vec<(usize, usize)> {
+        let cells = compute_alignment::<B, H, K>(self.current ^ self.mask)
+            | compute_alignment::<B, H, K>(self.current);
+
+        (0..W)
+            .flat_map(|colm| (0..H).map(move |row| (colm, row)))
+            .filter(|&(colm, row)| cells & (bottom_mask_colm::<B, H>(colm) << row) != B::ZERO)
+            .collect()
+    }
+
+    // row 0 is the bottom, cells outside of the board are empty
+    pub fn cell(&self, colm: usize, row: usize) -> Cell {
+        // checked before the shifts, which overflow outside of the board
+        if colm >= W || row >= H {
+            return Cell::Empty;
+        }
+        let cell = bottom_mask_colm::<B, H>(colm) << row;
+        if self.mask & cell == B::ZERO {
+            Cell::Empty
+        } else if (self.first_player_stones() & cell) != B::ZERO {
+            Cell::FirstPlayer
+        } else {
+            Cell::SecondPlayer
+        }
+    }
+
+    fn first_player_stones(&self) -> B {
+        if self.played_moves.is_multiple_of(2) {
+            self.current
+        } else {
+            self.current ^ self.mask
+        }
+    }
+
+    /*
+        parses the format written by Display, X is the first player and O the second,
+        the row with the column labels is optional
+    */
+    pub fn from_grid(grid: &str) -> Result<Self, ParsingGridErr> {
+        let mut rows: Vec<Vec<&str>> = grid
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .filter(|cells| !cells.is_empty())
+            .collect();
+        let is_label_row = |cells: &Vec<&str>| {
+            cells
+                .iter()
+                .all(|cell| cell.chars().all(|ch| ch.is_ascii_digit()))
+        };
+        if rows.last().is_some_and(is_label_row) {
+            rows.pop();
+        }
+        if rows.len() != H {
+            return Err(ParsingGridErr::RowCount(rows.len()));
+        }
+
+        let mut cells = [[Cell::Empty; H]; W];
+        // rows are written from the top
+        for (row, line) in rows.iter().rev().enumerate() {
+            if line.len() != W {
+                return Err(ParsingGridErr::RowLength {
+                    row,
+                    len: line.len(),
+                });
+            }
+            for (colm, &cell) in line.iter().enumerate() {
+                cells[colm][row] = match cell {
+                    "." => Cell::Empty,
+                    "X" | "x" => Cell::FirstPlayer,
+                    "O" | "o" => Cell::SecondPlayer,
+                    _ => return Err(ParsingGridErr::InvalidCell { colm, row }),
+                };
+            }
+        }
+
+        Self::from_cells(&cells).map_err(ParsingGridErr::InvalidPosition)
+    }
+
+    // cells[colm][row], row 0 is the bottom, the player to move follows from the stone count
+    pub fn from_cells(cells: &[[Cell; H]; W]) -> Result<Self, InvalidPositionErr> {
+        let mut first_player = B::ZERO;
+        let mut mask = B::ZERO;
+        for (colm, column) in cells.iter().enumerate() {
+            for (row, &cell) in column.iter().enumerate() {
+                let stone = bottom_mask_colm::<B, H>(colm) << row;
+                match cell {
+                    Cell::Empty => {}
+                    Cell::FirstPlayer => {
+                        first_player |= stone;
+                        mask |= stone;
+                    }
+                    Cell::SecondPlayer => mask |= stone,
+                }
+            }
+        }
+
+        let current = if mask.count_ones().is_multiple_of(2) {
+            first_player
+        } else {
+            first_player ^ mask
+        };
+        Self::from_bitboards(current, mask)
+    }
+
+    /*
+        current holds the stones of the player to move and mask all stones,
+        the position is accepted only if it can be reached in a game
+    */
+    pub fn from_bitboards(current: B, mask: B) -> Result<Self, InvalidPositionErr> {
+        let () = Self::FITS_IN_BITBOARD;
+        let () = Self::VALID_CONNECT;
+
+        if (mask & !Self::board_mask()) != B::ZERO || (current & !mask) != B::ZERO {
+            return Err(InvalidPositionErr::OutsideOfBoard);
+        }
+
+        for colm in 0..W {
+            for row in 1..H {
+                let stone = bottom_mask_colm::<B, H>(colm) << row;
+                if mask & stone != B::ZERO && mask & (stone >> 1) == B::ZERO {
+                    return Err(InvalidPositionErr::FloatingStone { colm, row });
+                }
+            }
+        }
+
+        let board = Self {
+            current,
+            mask,
+            played_moves: mask.count_ones() as usize,
+        };
+
+        // the first player moves first, so they have the same number of stones or one more
+        let first = board.first_player_stones().count_ones() as usize;
+        let second = board.played_moves - first;
+        if first != second && first != second + 1 {
+            return Err(InvalidPositionErr::StoneCount { first, second });
+        }
+
+        let last_player = current ^ mask;
+        let last_connected = compute_alignment::<B, H, K>(last_player) != B::ZERO;
+        let current_connected = compute_alignment::<B, H, K>(current) != B::ZERO;
+        if last_connected && current_connected {
+            return Err(InvalidPositionErr::BothPlayersConnected);
+        }
+        if current_connected {
+            return Err(InvalidPositionErr::ConnectedBeforeLastMove);
+        }
+        if last_connected {
+            // one of the top stones of the winner must have been the winning move
+            let winning_move_exists = (0..W).any(|colm| {
+                let stones = mask & column_mask::<B, H>(colm);
+                let top = (stones + bottom_mask_colm::<B, H>(colm)) >> 1;
+                stones != B::ZERO
+                    && last_player & top != B::ZERO
+                    && compute_alignment::<B, H, K>(last_player ^ top) == B::ZERO
+            });
+            if !winning_move_exists {
+                return Err(InvalidPositionErr::ConnectedBeforeLastMove);
+            }
+        }
+
+        Ok(board)
+    }
+
+    fn last_player_won(&self) -> GameStatus {
+        if self.played_moves % 2 == 1 {
+            GameStatus::FirstPlayerWon
+        } else {
+            GameStatus::SecondPlayerWon
+        }
+    }
+
+    pub fn can_win_next(&self) -> bool {
+        self.current_winning_moves() & self.possible() != B::ZERO
+    }
+
+    pub(crate) fn possible_non_losing_moves(&self) -> B {
         let mut possible = self.possible();
         let opponent_winning = self.opponent_winning_moves();
         // block or the opponent will win with next move
         let forced = possible & opponent_winning;
-        if forced != 0 {
+        if forced != B::ZERO {
             // bit trick to see if there is more than forced move
-            if forced & (forced - 1) != 0 {
+            if forced & (forced - B::ONE) != B::ZERO {
                 // we lose
-                return 0; 
+                return B::ZERO;
             } else {
                 possible = forced;
             }
@@ -106,102 +427,297 @@ impl Board {
         possible & (!(opponent_winning >> 1))
     }
 
-    pub(crate) fn play_move(&mut self, mov: u64) {
-        self.current = self.mask ^ self.current;
-        self.mask = self.mask | mov;
-        self.played_moves+=1;
+    pub(crate) fn play_move(&mut self, mov: B) {
+        self.current ^= self.mask;
+        self.mask |= mov;
+        self.played_moves += 1;
     }
 
-    pub(crate) fn score(&self, mov: u64) -> i32 {
-        popcount(compute_winning_position(self.current | mov, self.mask))
+    pub(crate) fn score(&self, mov: B) -> i32 {
+        compute_winning_position::<W, H, B, K>(self.current | mov, self.mask).count_ones() as i32
     }
 }
 
-fn top_mask_colm(colm: usize) -> u64 {
-    (1u64 << (HEIGHT - 1)) << colm * (HEIGHT + 1)
+pub(crate) fn top_mask_colm<B: Bitboard, const H: usize>(colm: usize) -> B {
+    (B::ONE << (H - 1)) << (colm * (H + 1))
 }
 
-fn bottom_mask_colm(colm: usize) -> u64 {
-    1u64 << colm * (HEIGHT + 1)
+pub(crate) fn bottom_mask_colm<B: Bitboard, const H: usize>(colm: usize) -> B {
+    B::ONE << (colm * (H + 1))
 }
 
-pub(crate) fn column_mask(colm: usize) -> u64 {
-    ((1u64 << HEIGHT) - 1) << colm * (HEIGHT + 1)
+pub(crate) fn column_mask<B: Bitboard, const H: usize>(colm: usize) -> B {
+    ((B::ONE << H) - B::ONE) << (colm * (H + 1))
 }
 
-fn compute_winning_position(position: u64, mask: u64) -> u64 {
-    // vertical - 3 under
-    let mut winning = (position << 1) & (position << 2) & (position << 3);
+// shifts which are as wide as the bitboard clear it instead of overflowing
+#[inline]
+fn shift_up<B: Bitboard>(position: B, shift: usize) -> B {
+    if shift < B::BITS {
+        position << shift
+    } else {
+        B::ZERO
+    }
+}
 
-    let horizontal_shift = HEIGHT + 1;
-    // xx_
-    let mut horizontal_pair = (position << horizontal_shift) & (position << (2 * horizontal_shift));
-    // xxx_
-    winning |= horizontal_pair & (position << (3 * horizontal_shift));
-    // xx_x
-    winning |= horizontal_pair & (position >> horizontal_shift);
-    // _xx
-    horizontal_pair = horizontal_pair >> (3 * horizontal_shift);
-    // _xxx
-    winning |= horizontal_pair & (position << horizontal_shift);
-    // x_xx
-    winning |= horizontal_pair & (position >> (3 * horizontal_shift));
+#[inline]
+fn shift_down<B: Bitboard>(position: B, shift: usize) -> B {
+    if shift < B::BITS {
+        position >> shift
+    } else {
+        B::ZERO
+    }
+}
 
-    let main_dialonal_shift = HEIGHT;
-    let mut main_dialonal_pair =
-        (position << main_dialonal_shift) & (position << (2 * main_dialonal_shift));
-    winning |= main_dialonal_pair & (position << (3 * main_dialonal_shift));
-    winning |= main_dialonal_pair & (position >> main_dialonal_shift);
-    main_dialonal_pair = main_dialonal_pair >> (3 * main_dialonal_shift);
-    winning |= main_dialonal_pair & (position << main_dialonal_shift);
-    winning |= main_dialonal_pair & (position >> (3 * main_dialonal_shift));
+// empty cells which would complete K aligned stones of position
+fn compute_winning_position<const W: usize, const H: usize, B: Bitboard, const K: usize>(
+    position: B,
+    mask: B,
+) -> B {
+    // vertical - K - 1 under
+    let mut winning = !B::ZERO;
+    for ind in 1..K {
+        winning &= shift_up(position, ind);
+    }
 
-    let off_dialonal_shift = HEIGHT + 2;
-    let mut off_dialonal_pair =
-        (position << off_dialonal_shift) & (position << (2 * off_dialonal_shift));
-    winning |= off_dialonal_pair & (position << (3 * off_dialonal_shift));
-    winning |= off_dialonal_pair & (position >> off_dialonal_shift);
-    off_dialonal_pair = off_dialonal_pair >> (3 * off_dialonal_shift);
-    winning |= off_dialonal_pair & (position << off_dialonal_shift);
-    winning |= off_dialonal_pair & (position >> (3 * off_dialonal_shift));
+    // horizontal, main diagonal, off diagonal
+    for shift in [H + 1, H, H + 2] {
+        // before[ind] - the ind cells before are stones, after[ind] - the ind cells after are stones
+        let mut before = [!B::ZERO; K];
+        let mut after = [!B::ZERO; K];
+        for ind in 1..K {
+            before[ind] = before[ind - 1] & shift_up(position, ind * shift);
+            after[ind] = after[ind - 1] & shift_down(position, ind * shift);
+        }
+        // the empty cell splits the line in ind stones before and K - 1 - ind after
+        for ind in 0..K {
+            winning |= before[ind] & after[K - 1 - ind];
+        }
+    }
 
-    return winning & (BOARD_MASK ^ mask);
+    winning & (Board::<W, H, B, K>::board_mask() ^ mask)
 }
 
-fn popcount(mut mask: u64) -> i32 {
-    let mut count = 0;
-    while mask != 0{
-        mask = mask & (mask - 1);
-        count+=1;
+// every cell which is part of K or more aligned stones of position
+pub(crate) fn compute_alignment<B: Bitboard, const H: usize, const K: usize>(position: B) -> B {
+    let mut cells = B::ZERO;
+    // vertical, horizontal, main diagonal, off diagonal
+    for shift in [1, H + 1, H, H + 2] {
+        // the lowest cell of every K in a row
+        let mut starts = position;
+        for ind in 1..K {
+            starts &= shift_down(position, ind * shift);
+        }
+        for ind in 0..K {
+            cells |= shift_up(starts, ind * shift);
+        }
     }
-    count
+    cells
 }
 
-impl FromStr for Board {
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> FromStr for Board<W, H, B, K> {
     type Err = ParsingBoardErr;
 
     fn from_str(encoded_board: &str) -> Result<Self, Self::Err> {
-        let mut board = Board::new();
-        for ch in encoded_board.as_bytes() {
-            let colm = (ch - '1' as u8) as usize;
-            if colm >= WIDTH || !board.can_play(colm) || board.is_winning(colm) {
-                return Err(ParsingBoardErr { msg: encoded_board.to_string() });
+        let mut board = Self::new();
+        for (index, ch) in encoded_board.chars().enumerate() {
+            let colm = parse_colm(index, ch)?;
+            if board.can_play(colm) && board.is_winning(colm) {
+                return Err(ParsingBoardErr::WinningMove { index });
             }
-            board.play(colm);
+            board
+                .try_play(colm)
+                .map_err(|err| ParsingBoardErr::from_move_err(index, err))?;
         }
         Ok(board)
     }
 }
 
-#[derive(Debug)]
-pub struct ParsingBoardErr {
-    msg: String,
+// columns are written as digits starting from 1
+pub(crate) fn parse_colm(index: usize, ch: char) -> Result<usize, ParsingBoardErr> {
+    let digit = ch
+        .to_digit(10)
+        .ok_or(ParsingBoardErr::InvalidCharacter { index, ch })?;
+    (digit as usize)
+        .checked_sub(1)
+        .ok_or(ParsingBoardErr::ColumnOutOfRange { index, colm: 0 })
+}
+
+/*
+    . . . . . . .
+    . . . . . . .
+    . . . . . . .
+    . . . X . . .
+    . . O X . . .
+    . . O X . . .
+    1 2 3 4 5 6 7
+*/
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Display for Board<W, H, B, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in (0..H).rev() {
+            let cells: Vec<&str> = (0..W)
+                .map(|colm| match self.cell(colm, row) {
+                    Cell::Empty => ".",
+                    Cell::FirstPlayer => "X",
+                    Cell::SecondPlayer => "O",
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        let labels: Vec<String> = (1..=W).map(|colm| (colm % 10).to_string()).collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
+
+// index is the position of the offending character, which is also the number of moves before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsingBoardErr {
+    InvalidCharacter { index: usize, ch: char },
+    // colm is the written column, starting from 1
+    ColumnOutOfRange { index: usize, colm: usize },
+    ColumnFull { index: usize, colm: usize },
+    // from Board::from_str, a Board cannot hold a finished game
+    WinningMove { index: usize },
+    // from GameRecord::from_str, which keeps the winning move but no move after it
+    GameAlreadyWon { index: usize },
+}
+
+impl ParsingBoardErr {
+    pub(crate) fn from_move_err(index: usize, err: InvalidMoveErr) -> Self {
+        match err {
+            InvalidMoveErr::ColumnOutOfRange(colm) => ParsingBoardErr::ColumnOutOfRange {
+                index,
+                colm: colm + 1,
+            },
+            InvalidMoveErr::ColumnFull(colm) => ParsingBoardErr::ColumnFull {
+                index,
+                colm: colm + 1,
+            },
+            InvalidMoveErr::GameOver => ParsingBoardErr::GameAlreadyWon { index },
+            // only PopOutBoard pops, the digits of the notation are drops
+            InvalidMoveErr::CannotPop(_) => unreachable!("the notation has no pops"),
+        }
+    }
 }
 
 impl Display for ParsingBoardErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Invalid board: {}", self.msg)
+        match self {
+            ParsingBoardErr::InvalidCharacter { index, ch } => {
+                write!(f, "Invalid character '{ch}' at index {index}")
+            }
+            ParsingBoardErr::ColumnOutOfRange { index, colm } => {
+                write!(f, "Column {colm} at index {index} is outside of the board")
+            }
+            ParsingBoardErr::ColumnFull { index, colm } => {
+                write!(f, "Column {colm} at index {index} is full")
+            }
+            ParsingBoardErr::WinningMove { index } => {
+                write!(f, "The move at index {index} wins the game")
+            }
+            ParsingBoardErr::GameAlreadyWon { index } => {
+                write!(f, "The game was already won at ply {index}")
+            }
+        }
     }
 }
 
 impl Error for ParsingBoardErr {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidMoveErr {
+    ColumnOutOfRange(usize),
+    ColumnFull(usize),
+    // popping is only allowed for the own stone at the bottom of a column
+    CannotPop(usize),
+    GameOver,
+}
+
+// columns are shown from 1, any usize can be out of range
+impl Display for InvalidMoveErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidMoveErr::ColumnOutOfRange(colm) => {
+                write!(
+                    f,
+                    "Column {} is outside of the board",
+                    colm.saturating_add(1)
+                )
+            }
+            InvalidMoveErr::ColumnFull(colm) => {
+                write!(f, "Column {} is full", colm.saturating_add(1))
+            }
+            InvalidMoveErr::CannotPop(colm) => {
+                write!(
+                    f,
+                    "The bottom of column {} is not your stone",
+                    colm.saturating_add(1)
+                )
+            }
+            InvalidMoveErr::GameOver => write!(f, "The game is over"),
+        }
+    }
+}
+
+impl Error for InvalidMoveErr {}
+
+// rows are counted from the bottom, like in Board::cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsingGridErr {
+    RowCount(usize),
+    RowLength { row: usize, len: usize },
+    InvalidCell { colm: usize, row: usize },
+    InvalidPosition(InvalidPositionErr),
+}
+
+impl Display for ParsingGridErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsingGridErr::RowCount(count) => write!(f, "Invalid number of rows: {count}"),
+            ParsingGridErr::RowLength { row, len } => {
+                write!(f, "Row {} has {} cells", row + 1, len)
+            }
+            ParsingGridErr::InvalidCell { colm, row } => {
+                write!(f, "Invalid cell in column {} row {}", colm + 1, row + 1)
+            }
+            ParsingGridErr::InvalidPosition(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for ParsingGridErr {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPositionErr {
+    OutsideOfBoard,
+    FloatingStone { colm: usize, row: usize },
+    StoneCount { first: usize, second: usize },
+    BothPlayersConnected,
+    // the player to move has a line or the line of the last player was not completed by the last move
+    ConnectedBeforeLastMove,
+}
+
+impl Display for InvalidPositionErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPositionErr::OutsideOfBoard => write!(f, "Stones outside of the board"),
+            InvalidPositionErr::FloatingStone { colm, row } => {
+                write!(
+                    f,
+                    "Stone without support in column {} row {}",
+                    colm + 1,
+                    row + 1
+                )
+            }
+            InvalidPositionErr::StoneCount { first, second } => {
+                write!(f, "Invalid stone count: {first} X and {second} O")
+            }
+            InvalidPositionErr::BothPlayersConnected => write!(f, "Both players have a line"),
+            InvalidPositionErr::ConnectedBeforeLastMove => {
+                write!(f, "The game was already won before the last move")
+            }
+        }
+    }
+}
+
+impl Error for InvalidPositionErr {}
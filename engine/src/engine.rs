@@ -1,114 +1,571 @@
 use crate::{
-    board::{Board, HEIGHT, WIDTH, column_mask},
+    bitboard::Bitboard,
+    board::{Board, CONNECT, GameStatus, HEIGHT, WIDTH, column_mask},
     move_sorter::MoveSorter,
     opening_book::OpeningBook,
+    outcome::Outcome,
+    popout::{PopOutBoard, PopOutGame, PopOutMove},
+    progress::{SearchObserver, SearchProgress},
     transposition_table::TranspositionTable,
 };
-use std::cmp::max;
+use std::{
+    cmp::max,
+    error::Error,
+    fmt::Display,
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// 2796181 buckets of 4 entries with one key word for the 7x6 board
+const DEFAULT_TABLE_MEMORY_MB: usize = 64;
+/*
+    set on the values of the transposition table which are lower bounds (beta cut-offs),
+    the others are upper bounds (fail-low), the scores of any board fit in the other 7 bits
+*/
+const LOWER_BOUND_FLAG: u8 = 0x80;
+// nodes between two checks of the search budget
+const BUDGET_CHECK_INTERVAL: usize = 1024;
+// minimal time between two reports to the observer
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// the best move from the table is searched first
+const HINT_PRIORITY: i32 = 1 << 30;
+// the history is halved when a cell reaches it, so it only breaks the ties of Board::score
+const MAX_HISTORY: u32 = 1 << 16;
+
+/*
+    result of Engine::analyze, columns start from 0 and the score is
+    from the point of view of the player to move, same as Engine::score
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    // every column which reaches the score, from left to right
+    pub best_moves: Vec<usize>,
+    pub score: i32,
+    pub outcome: Outcome,
+    // plies until the win, the loss or the last move of a draw, with perfect play
+    pub plies_to_end: usize,
+    // one sequence of perfect moves from the position to the end of the game
+    pub principal_variation: Vec<usize>,
+}
+
+// shared flag to stop a search from another thread, a cancelled token stays cancelled
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The search was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+// no limit means the search goes on until the position is solved
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub time: Option<Duration>,
+    pub nodes: Option<usize>,
+    pub cancel: Option<CancellationToken>,
+}
+
+/*
+    result of Engine::search, the score is exact when proven, otherwise it is
+    the score at the reached depth where the unresolved positions count as draws
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: usize,
+    pub score: i32,
+    pub proven: bool,
+    // last completed depth, 0 if not even the first one completed
+    pub depth: usize,
+}
 
-const MIN_SCORE: i32 = -(WIDTH as i32 * HEIGHT as i32) / 2 + 3;
-// const MAX_SCORE: i32 = (WIDTH as i32 * HEIGHT as i32 + 1) / 2 - 3;
+// limits of the running search in absolute terms, checked by negamax
+#[derive(Clone, Default)]
+struct Budget {
+    deadline: Option<Instant>,
+    max_nodes: Option<usize>,
+    cancel: Option<CancellationToken>,
+}
+
+impl Budget {
+    fn cancellable(cancel: &CancellationToken) -> Self {
+        Self {
+            cancel: Some(cancel.clone()),
+            ..Self::default()
+        }
+    }
+
+    fn is_exhausted(&self, explored_nodes: usize) -> bool {
+        self.max_nodes
+            .is_some_and(|max_nodes| explored_nodes >= max_nodes)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.is_cancelled())
+    }
+}
 
-// nearest prime to 8 * 1024 * 1024
-const TRANSPOSITION_TABLE_SIZE: usize = 8388593;
+// counters of the running search, reported to the observer
+#[derive(Clone)]
+struct Progress {
+    start: Instant,
+    last_report: Instant,
+    // counters of the engine when the search started
+    nodes: usize,
+    table_lookups: usize,
+    table_hits: usize,
+    window: Option<(i32, i32)>,
+    depth: Option<usize>,
+}
 
 #[derive(Clone)]
-pub struct Engine {
-    column_order: [usize; WIDTH],
-    table: TranspositionTable,
-    book: OpeningBook,
+pub struct Engine<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    column_order: [usize; W],
+    // shared with the clones of the engine and the helper threads
+    table: Arc<TranspositionTable<B>>,
+    book: OpeningBook<W, H, B, K>,
     explored_nodes: usize,
+    budget: Budget,
+    // number of positions cut by a depth limit, to know if a result is proven
+    horizon_hits: usize,
+    table_lookups: usize,
+    table_hits: usize,
+    observer: Option<Arc<dyn SearchObserver>>,
+    progress: Option<Progress>,
+    // searching threads, including the calling one
+    threads: usize,
+    // move ordering options, see EngineBuilder
+    history_heuristic: bool,
+    killer_moves: bool,
+    // beta cut-offs of each player by cell, weighted by the moves left
+    history: Vec<u32>,
+    // last two columns which cut for each number of played moves, W if none
+    killers: Vec<[usize; 2]>,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default for Engine<W, H, B, K> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Engine {
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Engine<W, H, B, K> {
+    // a player needs at least K stones to win
+    const MIN_SCORE: i32 = -(W as i32 * H as i32) / 2 + K as i32 - 1;
+    // const MAX_SCORE: i32 = (W as i32 * H as i32 + 1) / 2 - K as i32 + 1;
+
     pub fn new() -> Self {
-        let mut column_order = [0; WIDTH];
+        Self::builder().build()
+    }
 
-        // [3, 2, 4, 1, 5, 0, 6]
-        for ind in 0..(WIDTH as i32) {
-            let colm = WIDTH as i32 / 2 + (1 - 2 * (ind & 1)) * (ind + 1) / 2;
-            column_order[ind as usize] = colm as usize;
-        }
+    pub fn builder() -> EngineBuilder<W, H, B, K> {
+        EngineBuilder::new()
+    }
 
-        Self {
-            column_order,
-            table: TranspositionTable::new(TRANSPOSITION_TABLE_SIZE),
-            book: OpeningBook::new(),
-            explored_nodes: 0,
-        }
+    pub fn with_book(book: OpeningBook<W, H, B, K>) -> Self {
+        Self::builder().book(book).build()
+    }
+
+    // engines on the same table reuse each other's results, even from other threads
+    pub fn with_table(table: Arc<TranspositionTable<B>>) -> Self {
+        Self::builder().table(table).build()
+    }
+
+    pub fn table(&self) -> Arc<TranspositionTable<B>> {
+        self.table.clone()
+    }
+
+    /*
+        number of threads used by score and solve, 1 searches on the calling thread only,
+        a speedup over 1 thread has not been measured yet, on a single core the helpers
+        only add nodes (4 threads took 2.3 times as long on the eval positions)
+    */
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
     }
 
-    pub fn with_book(book: OpeningBook) -> Self {
-        let mut engine = Self::new();
-        engine.book = book;
-        engine
+    // the observer is kept by the clones of the engine
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn SearchObserver>>) {
+        self.observer = observer;
     }
 
-    fn negamax(&mut self, board: Board, mut alpha: i32, mut beta: i32) -> i32 {
+    /*
+        None if the budget ran out, depth is the number of plies searched before
+        a position is given the heuristic score 0, in which case nothing is stored
+        in the table as the result is not proven
+    */
+    fn negamax(
+        &mut self,
+        board: Board<W, H, B, K>,
+        mut alpha: i32,
+        mut beta: i32,
+        depth: usize,
+    ) -> Option<i32> {
         self.explored_nodes += 1;
+        if self.explored_nodes.is_multiple_of(BUDGET_CHECK_INTERVAL) {
+            if self.budget.is_exhausted(self.explored_nodes) {
+                return None;
+            }
+            self.report_progress(false, false);
+        }
 
         let next = board.possible_non_losing_moves();
-        if next == 0 {
-            return -(((WIDTH * HEIGHT - board.played_moves()) / 2) as i32);
+        if next == B::ZERO {
+            return Some(-(((W * H - board.played_moves()) / 2) as i32));
         }
 
-        if board.played_moves() >= WIDTH * HEIGHT - 2 {
+        if board.played_moves() >= W * H - 2 {
             // draw
-            return 0;
+            return Some(0);
         }
 
         // opponent cannot win with his next move
-        let lower_bound = -(((WIDTH * HEIGHT - board.played_moves() - 2) / 2) as i32);
+        let lower_bound = -(((W * H - board.played_moves() - 2) / 2) as i32);
         if alpha < lower_bound {
             alpha = lower_bound;
             if alpha >= beta {
-                return alpha;
+                return Some(alpha);
             }
         }
 
-        let mut upper_bound = (WIDTH * HEIGHT - board.played_moves() - 1) as i32 / 2;
-        if let Some(value) = self.table.get(board.key()) {
-            upper_bound = value as i32 + MIN_SCORE as i32 - 1;
+        let key = board.canonical_key();
+        let mut upper_bound = (W * H - board.played_moves() - 1) as i32 / 2;
+        let mut hint = None;
+        self.table_lookups += 1;
+        if let Some((value, best_move)) = self.table.get(key) {
+            self.table_hits += 1;
+            hint = Self::hint(&board, key, best_move);
+            let bound = (value & !LOWER_BOUND_FLAG) as i32 + Self::MIN_SCORE - 1;
+            if value & LOWER_BOUND_FLAG == 0 {
+                upper_bound = bound;
+            } else if alpha < bound {
+                alpha = bound;
+                if alpha >= beta {
+                    return Some(alpha);
+                }
+            }
         }
         if upper_bound < beta {
             beta = upper_bound;
             if alpha >= beta {
-                return beta;
+                return Some(beta);
             }
         }
 
-        let mut moves = MoveSorter::new();
-        for ind in (0..WIDTH).rev() {
+        if depth == 0 {
+            self.horizon_hits += 1;
+            return Some(0.clamp(alpha, beta));
+        }
+
+        let mut moves = MoveSorter::<W, B>::new();
+        for ind in (0..W).rev() {
             let colm = self.column_order[ind];
-            let mov = next & column_mask(colm);
-            if mov != 0 {
-                moves.add(mov, board.score(mov));
+            let mov = next & column_mask::<B, H>(colm);
+            if mov != B::ZERO {
+                moves.add(mov, colm, self.move_priority(&board, mov, colm, hint));
             }
         }
 
-        for mov in moves {
-            let mut board = board.clone();
-            board.play_move(mov);
-            alpha = max(alpha, -self.negamax(board, -beta, -alpha));
+        let horizon_hits = self.horizon_hits;
+        for (mov, colm) in moves {
+            let mut child = board;
+            child.play_move(mov);
+            alpha = max(alpha, -self.negamax(child, -beta, -alpha, depth - 1)?);
             if alpha >= beta {
-                return alpha;
+                self.record_cut_off(&board, mov, colm);
+                if self.horizon_hits == horizon_hits {
+                    self.store_bound(&board, key, alpha, colm, true);
+                }
+                return Some(alpha);
             }
         }
-        self.table.put(board.key(), (alpha - MIN_SCORE + 1) as u8);
-        return alpha;
+        if self.horizon_hits == horizon_hits {
+            // no move is better than the others after a fail-low
+            self.store_bound(&board, key, alpha, W, false);
+        }
+        Some(alpha)
+    }
+
+    /*
+        the table stores the columns of the canonical position, an entry written
+        by another thread may hold a column of another position
+    */
+    pub(crate) fn hint(board: &Board<W, H, B, K>, key: B, best_move: usize) -> Option<usize> {
+        (best_move < W).then(|| {
+            if key != board.key() {
+                W - 1 - best_move
+            } else {
+                best_move
+            }
+        })
+    }
+
+    // values are shifted to start from 1, 0 marks the empty entries, best_move is W if none
+    pub(crate) fn store_bound(
+        &self,
+        board: &Board<W, H, B, K>,
+        key: B,
+        bound: i32,
+        best_move: usize,
+        lower: bool,
+    ) {
+        let mut value = (bound - Self::MIN_SCORE + 1) as u8;
+        if lower {
+            value |= LOWER_BOUND_FLAG;
+        }
+        let best_move = if key != board.key() && best_move < W {
+            W - 1 - best_move
+        } else {
+            best_move
+        };
+        self.table.put(key, value, best_move, board.played_moves());
+    }
+
+    // moves of higher priority are searched first
+    fn move_priority(
+        &self,
+        board: &Board<W, H, B, K>,
+        mov: B,
+        colm: usize,
+        hint: Option<usize>,
+    ) -> i32 {
+        if hint == Some(colm) {
+            return HINT_PRIORITY;
+        }
+        // the killer moves come before the others of the same score
+        let mut priority = board.score(mov) * 2 * MAX_HISTORY as i32;
+        if self.killer_moves {
+            let killers = self.killers[board.played_moves()];
+            if let Some(ind) = killers.iter().position(|&killer| killer == colm) {
+                return priority + MAX_HISTORY as i32 + 1 - ind as i32;
+            }
+        }
+        if self.history_heuristic {
+            priority += self.history[self.history_index(board, mov)] as i32;
+        }
+        priority
+    }
+
+    // cell of the move for the player to move
+    fn history_index(&self, board: &Board<W, H, B, K>, mov: B) -> usize {
+        let cell = (mov - B::ONE).count_ones() as usize;
+        (board.played_moves() % 2) * W * (H + 1) + cell
     }
 
-    pub fn score(&mut self, board: Board) -> i32 {
-        if let Some(score) = self.book.score(&board) {
-            return score;
+    fn record_cut_off(&mut self, board: &Board<W, H, B, K>, mov: B, colm: usize) {
+        if self.killer_moves {
+            let killers = &mut self.killers[board.played_moves()];
+            if killers[0] != colm {
+                killers[1] = killers[0];
+                killers[0] = colm;
+            }
+        }
+        if self.history_heuristic {
+            let moves_left = (W * H - board.played_moves()) as u32;
+            let ind = self.history_index(board, mov);
+            self.history[ind] += moves_left * moves_left;
+            if self.history[ind] >= MAX_HISTORY {
+                self.history.iter_mut().for_each(|history| *history /= 2);
+            }
+        }
+    }
+
+    // the game must not be over, see Board::is_game_over, same for the other scores
+    pub fn score(&mut self, board: Board<W, H, B, K>) -> i32 {
+        Self::assert_ongoing(&board);
+        // there is no budget outside of search and the try_ methods
+        self.run(Budget::default(), |engine| {
+            engine.budgeted_score(board, false)
+        })
+        .unwrap()
+    }
+
+    // score which another thread can stop with the token
+    pub fn try_score(
+        &mut self,
+        board: Board<W, H, B, K>,
+        cancel: &CancellationToken,
+    ) -> Result<i32, Cancelled> {
+        Self::assert_ongoing(&board);
+        self.run(Budget::cancellable(cancel), |engine| {
+            engine.budgeted_score(board, false)
+        })
+        .ok_or(Cancelled)
+    }
+
+    /*
+        1 if the player to move wins, 0 for a draw and -1 if they lose, the sign of score
+        found with a single search instead of one per null window, so much faster in the opening
+    */
+    pub fn weak_score(&mut self, board: Board<W, H, B, K>) -> i32 {
+        Self::assert_ongoing(&board);
+        self.run(Budget::default(), |engine| {
+            engine.budgeted_score(board, true)
+        })
+        .unwrap()
+    }
+
+    pub fn try_weak_score(
+        &mut self,
+        board: Board<W, H, B, K>,
+        cancel: &CancellationToken,
+    ) -> Result<i32, Cancelled> {
+        Self::assert_ongoing(&board);
+        self.run(Budget::cancellable(cancel), |engine| {
+            engine.budgeted_score(board, true)
+        })
+        .ok_or(Cancelled)
+    }
+
+    // a finished game has no score, the searches would score moves played after its end
+    fn assert_ongoing(board: &Board<W, H, B, K>) {
+        assert!(!board.is_game_over(), "the game is already over");
+    }
+
+    // the sign of the score when weak
+    fn budgeted_score(&mut self, board: Board<W, H, B, K>, weak: bool) -> Option<i32> {
+        let search = if weak {
+            Self::weak_search
+        } else {
+            Self::binary_search
+        };
+
+        if let Some(score) = self.book_score(&board, weak) {
+            return Some(if weak { score.signum() } else { score });
         }
 
         if board.can_win_next() {
-            return (WIDTH * HEIGHT - board.played_moves() + 1) as i32 / 2;
+            return Some(Self::winning_score(&board, weak));
+        }
+
+        if self.threads > 1 {
+            self.parallel_search(board, search)
+        } else {
+            search(self, board)
         }
+    }
 
-        let mut left = -((WIDTH * HEIGHT - board.played_moves()) as i32) / 2;
-        let mut right = (WIDTH * HEIGHT - board.played_moves() + 1) as i32 / 2;
+    // the scores of a weak book are only signs, which exact searches cannot use
+    fn book_score(&self, board: &Board<W, H, B, K>, weak: bool) -> Option<i32> {
+        if self.book.is_weak() && !weak {
+            return None;
+        }
+        self.book.score(board)
+    }
+
+    // score of a board where the player to move wins with their next move
+    fn winning_score(board: &Board<W, H, B, K>, weak: bool) -> i32 {
+        if weak {
+            1
+        } else {
+            (W * H - board.played_moves() + 1) as i32 / 2
+        }
+    }
+
+    /*
+        Lazy SMP, the helpers search the same position with other move orders
+        and fill the shared table for the calling thread, whose result is kept
+    */
+    fn parallel_search(
+        &mut self,
+        board: Board<W, H, B, K>,
+        search: fn(&mut Self, Board<W, H, B, K>) -> Option<i32>,
+    ) -> Option<i32> {
+        let stop = CancellationToken::new();
+        let helpers: Vec<Self> = (1..self.threads)
+            .map(|ind| self.helper(ind, &stop))
+            .collect();
+
+        thread::scope(|s| {
+            let handles: Vec<_> = helpers
+                .into_iter()
+                .map(|mut helper| {
+                    s.spawn(move || {
+                        search(&mut helper, board);
+                        helper
+                    })
+                })
+                .collect();
+
+            let result = search(self, board);
+            stop.cancel();
+            for handle in handles {
+                let helper = handle.join().unwrap();
+                self.explored_nodes += helper.explored_nodes;
+                self.table_lookups += helper.table_lookups;
+                self.table_hits += helper.table_hits;
+            }
+            result
+        })
+    }
+
+    /*
+        an engine on the same table, the digits of ind in the factorial number system
+        pick the swaps of its move order so that no two of the first W! - 1 helpers share one
+    */
+    fn helper(&self, ind: usize, stop: &CancellationToken) -> Self {
+        let mut column_order = self.column_order;
+        let mut digits = ind;
+        for pos in 0..W {
+            column_order.swap(pos, pos + digits % (W - pos));
+            digits /= W - pos;
+        }
+        Self {
+            column_order,
+            table: self.table.clone(),
+            // the book is only read before the search starts
+            book: OpeningBook::new(),
+            explored_nodes: 0,
+            budget: Budget::cancellable(stop),
+            horizon_hits: 0,
+            table_lookups: 0,
+            table_hits: 0,
+            observer: None,
+            progress: None,
+            threads: 1,
+            history_heuristic: self.history_heuristic,
+            killer_moves: self.killer_moves,
+            history: vec![0; self.history.len()],
+            killers: vec![[W; 2]; self.killers.len()],
+        }
+    }
+
+    // null window searches around the score until it is found
+    fn binary_search(&mut self, board: Board<W, H, B, K>) -> Option<i32> {
+        let mut left = -((W * H - board.played_moves()) as i32) / 2;
+        let mut right = (W * H - board.played_moves() + 1) as i32 / 2;
 
         while left < right {
             let mut median = left + (right - left) / 2;
@@ -117,7 +574,8 @@ impl Engine {
             } else if median >= 0 && median < right / 2 {
                 median = right / 2;
             }
-            let score = self.negamax(board, median, median + 1);
+            self.start_step(Some((median, median + 1)), None);
+            let score = self.negamax(board, median, median + 1, usize::MAX)?;
             if score <= median {
                 right = score;
             } else {
@@ -125,31 +583,545 @@ impl Engine {
             }
         }
 
-        left
+        Some(left)
+    }
+
+    // a single search in the (-1, 1) window, which only separates wins, draws and losses
+    fn weak_search(&mut self, board: Board<W, H, B, K>) -> Option<i32> {
+        self.start_step(Some((-1, 1)), None);
+        Some(self.negamax(board, -1, 1, usize::MAX)?.signum())
+    }
+
+    /*
+        the outcome of playing each column, None for full columns
+        and for every column once the game is over
+    */
+    pub fn solve(&mut self, board: Board<W, H, B, K>) -> [Option<Outcome>; W] {
+        self.solve_scores(board)
+            .map(|score| score.and_then(|score| board.outcome(score)))
     }
 
-    pub fn solve(&mut self, board: Board) -> [Option<i32>; WIDTH] {
-        let mut result = [None; WIDTH];
+    // solve which another thread can stop with the token
+    pub fn try_solve(
+        &mut self,
+        board: Board<W, H, B, K>,
+        cancel: &CancellationToken,
+    ) -> Result<[Option<Outcome>; W], Cancelled> {
+        let scores = self
+            .run(Budget::cancellable(cancel), |engine| {
+                engine.budgeted_solve_scores(board, false)
+            })
+            .ok_or(Cancelled)?;
+        Ok(scores.map(|score| score.and_then(|score| board.outcome(score))))
+    }
+
+    // same as solve with raw scores
+    pub fn solve_scores(&mut self, board: Board<W, H, B, K>) -> [Option<i32>; W] {
+        self.run(Budget::default(), |engine| {
+            engine.budgeted_solve_scores(board, false)
+        })
+        .unwrap()
+    }
+
+    // same as solve_scores with the weak scores of weak_score
+    pub fn weak_solve_scores(&mut self, board: Board<W, H, B, K>) -> [Option<i32>; W] {
+        self.run(Budget::default(), |engine| {
+            engine.budgeted_solve_scores(board, true)
+        })
+        .unwrap()
+    }
+
+    pub fn try_weak_solve_scores(
+        &mut self,
+        board: Board<W, H, B, K>,
+        cancel: &CancellationToken,
+    ) -> Result<[Option<i32>; W], Cancelled> {
+        self.run(Budget::cancellable(cancel), |engine| {
+            engine.budgeted_solve_scores(board, true)
+        })
+        .ok_or(Cancelled)
+    }
+
+    fn budgeted_solve_scores(
+        &mut self,
+        board: Board<W, H, B, K>,
+        weak: bool,
+    ) -> Option<[Option<i32>; W]> {
+        let mut result = [None; W];
+        if board.is_game_over() {
+            return Some(result);
+        }
         for (colm, colm_result) in result.iter_mut().enumerate() {
             if board.can_play(colm) {
                 if board.is_winning(colm) {
-                   *colm_result = Some((WIDTH * HEIGHT - board.played_moves() + 1) as i32 / 2);
+                    *colm_result = Some(Self::winning_score(&board, weak));
                 } else {
-                    let mut board = board.clone();
+                    let mut board = board;
                     board.play(colm);
-                    *colm_result = Some(-self.score(board))
+                    *colm_result = Some(-self.budgeted_score(board, weak)?)
+                }
+            }
+        }
+        Some(result)
+    }
+
+    // runs a search within the budget and reports its progress to the observer
+    fn run<T>(&mut self, budget: Budget, search: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let now = Instant::now();
+        self.budget = budget;
+        self.progress = Some(Progress {
+            start: now,
+            last_report: now,
+            nodes: self.explored_nodes,
+            table_lookups: self.table_lookups,
+            table_hits: self.table_hits,
+            window: None,
+            depth: None,
+        });
+
+        let result = search(self);
+
+        self.report_progress(true, true);
+        self.budget = Budget::default();
+        self.progress = None;
+        result
+    }
+
+    // a null window or a depth starts, which is always reported
+    fn start_step(&mut self, window: Option<(i32, i32)>, depth: Option<usize>) {
+        if let Some(progress) = &mut self.progress {
+            progress.window = window;
+            progress.depth = depth;
+        }
+        self.report_progress(true, false);
+    }
+
+    fn report_progress(&mut self, force: bool, finished: bool) {
+        let (Some(observer), Some(progress)) = (&self.observer, &mut self.progress) else {
+            return;
+        };
+        let now = Instant::now();
+        if !force && now - progress.last_report < PROGRESS_INTERVAL {
+            return;
+        }
+        progress.last_report = now;
+
+        let nodes = self.explored_nodes - progress.nodes;
+        let elapsed = now - progress.start;
+        let table_lookups = self.table_lookups - progress.table_lookups;
+        let table_hits = self.table_hits - progress.table_hits;
+        observer.on_progress(&SearchProgress {
+            window: progress.window,
+            depth: progress.depth,
+            nodes,
+            nodes_per_second: nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            table_hit_rate: if table_lookups == 0 {
+                0.0
+            } else {
+                table_hits as f64 / table_lookups as f64
+            },
+            elapsed,
+            finished,
+        });
+    }
+
+    /*
+        iterative deepening until the position is solved or the limits are reached,
+        which are checked every BUDGET_CHECK_INTERVAL nodes, None if the game is over,
+        a cancelled search returns its last completed depth like an exhausted one
+    */
+    pub fn search(
+        &mut self,
+        board: Board<W, H, B, K>,
+        limits: SearchLimits,
+    ) -> Option<SearchResult> {
+        if board.is_game_over() {
+            return None;
+        }
+
+        // played if not even the first depth completes
+        let best_move = self
+            .column_order
+            .into_iter()
+            .find(|&colm| board.can_play(colm))?;
+
+        let budget = Budget {
+            deadline: limits.time.map(|time| Instant::now() + time),
+            max_nodes: limits.nodes.map(|nodes| self.explored_nodes + nodes),
+            cancel: limits.cancel,
+        };
+        self.run(budget, |engine| {
+            let mut result = SearchResult {
+                best_move,
+                score: 0,
+                proven: false,
+                depth: 0,
+            };
+            for depth in 1..=W * H - board.played_moves() {
+                engine.start_step(None, Some(depth));
+                let Some(iteration) = engine.search_depth(board, depth) else {
+                    break;
+                };
+                result = iteration;
+                if result.proven {
+                    break;
                 }
             }
+            Some(result)
+        })
+    }
+
+    // one iteration of search, None if the budget ran out
+    fn search_depth(&mut self, board: Board<W, H, B, K>, depth: usize) -> Option<SearchResult> {
+        let horizon_hits = self.horizon_hits;
+        let mut best: Option<(usize, i32)> = None;
+        let beta = (W * H) as i32;
+
+        for colm in self.column_order {
+            if !board.can_play(colm) {
+                continue;
+            }
+            let alpha = best.map_or(-beta, |(_, score)| score);
+            let score = if board.is_winning(colm) {
+                (W * H - board.played_moves() + 1) as i32 / 2
+            } else {
+                let mut child = board;
+                child.play(colm);
+                if let Some(score) = self.book_score(&child, false) {
+                    -score
+                } else if child.can_win_next() {
+                    -((W * H - child.played_moves() + 1) as i32 / 2)
+                } else {
+                    -self.negamax(child, -beta, -alpha, depth - 1)?
+                }
+            };
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((colm, score));
+            }
+        }
+
+        let (best_move, score) = best?;
+        Some(SearchResult {
+            best_move,
+            score,
+            proven: self.horizon_hits == horizon_hits,
+            depth,
+        })
+    }
+
+    // None if the game is already over
+    pub fn analyze(&mut self, board: Board<W, H, B, K>) -> Option<Analysis> {
+        if board.is_game_over() {
+            return None;
+        }
+
+        let scores = self.solve_scores(board);
+        let score = scores.iter().flatten().copied().max()?;
+        let best_moves = (0..W).filter(|&colm| scores[colm] == Some(score)).collect();
+
+        let outcome = board.outcome(score)?;
+        Some(Analysis {
+            best_moves,
+            score,
+            outcome,
+            plies_to_end: outcome.plies(W * H - board.played_moves()),
+            principal_variation: self.principal_variation(board, score),
+        })
+    }
+
+    // follows at each ply the first move in column order which keeps the score
+    fn principal_variation(&mut self, mut board: Board<W, H, B, K>, mut score: i32) -> Vec<usize> {
+        let mut variation = Vec::new();
+        while !board.is_game_over() {
+            if let Some(colm) = (0..W).find(|&colm| board.can_play(colm) && board.is_winning(colm))
+            {
+                variation.push(colm);
+                break;
+            }
+
+            let column_order = self.column_order;
+            let Some(colm) = column_order.into_iter().find(|&colm| {
+                if !board.can_play(colm) {
+                    return false;
+                }
+                let mut child = board;
+                child.play(colm);
+                // the score of a child is never below -score
+                self.is_at_most(child, -score)
+            }) else {
+                break;
+            };
+
+            variation.push(colm);
+            board.play(colm);
+            score = -score;
+        }
+        variation
+    }
+
+    // null window search, cheaper than computing the exact score
+    fn is_at_most(&mut self, board: Board<W, H, B, K>, bound: i32) -> bool {
+        if let Some(score) = self.book_score(&board, false) {
+            return score <= bound;
+        }
+        if board.can_win_next() {
+            return (W * H - board.played_moves() + 1) as i32 / 2 <= bound;
+        }
+        self.negamax(board, bound, bound + 1, usize::MAX).unwrap() <= bound
+    }
+
+    /*
+        PopOut games can go on forever, so the search is limited to depth plies
+        and a position which repeats one from the game or the search path is a draw,
+        the score is positive if the current player wins, higher for faster wins
+        (depth + 1 - plies to the win) and 0 for draws or if nothing is found within depth
+    */
+    pub fn score_popout(&mut self, game: &PopOutGame<W, H, B, K>, depth: usize) -> i32 {
+        let mut path = game.history().to_vec();
+        let board = path.pop().unwrap();
+        let bound = depth as i32 + 1;
+        self.popout_negamax(board, depth, -bound, bound, &mut path)
+    }
+
+    // the score of every legal move, same scale as score_popout
+    pub fn solve_popout(
+        &mut self,
+        game: &PopOutGame<W, H, B, K>,
+        depth: usize,
+    ) -> Vec<(PopOutMove, i32)> {
+        let mut path = game.history().to_vec();
+        let board = path.pop().unwrap();
+        if board.is_game_over() || depth == 0 {
+            return Vec::new();
+        }
+
+        path.push(board);
+        let bound = depth as i32 + 1;
+        let mut result = Vec::new();
+        for mov in self.popout_moves(&board) {
+            let mut child = board;
+            child.play(mov);
+            let score = -self.popout_negamax(child, depth - 1, -bound, bound, &mut path);
+            result.push((mov, score));
         }
         result
     }
 
+    fn popout_negamax(
+        &mut self,
+        board: PopOutBoard<W, H, B, K>,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        path: &mut Vec<PopOutBoard<W, H, B, K>>,
+    ) -> i32 {
+        self.explored_nodes += 1;
+
+        let first_to_move = board.played_moves().is_multiple_of(2);
+        match board.status() {
+            GameStatus::Ongoing => {}
+            GameStatus::Draw => return 0,
+            GameStatus::FirstPlayerWon if first_to_move => return depth as i32 + 1,
+            GameStatus::SecondPlayerWon if !first_to_move => return depth as i32 + 1,
+            _ => return -(depth as i32 + 1),
+        }
+
+        if depth == 0 || path.iter().any(|prev| prev.is_same_position(&board)) {
+            return 0;
+        }
+
+        path.push(board);
+        for mov in self.popout_moves(&board) {
+            let mut child = board;
+            child.play(mov);
+            alpha = max(
+                alpha,
+                -self.popout_negamax(child, depth - 1, -beta, -alpha, path),
+            );
+            if alpha >= beta {
+                break;
+            }
+        }
+        path.pop();
+        alpha
+    }
+
+    // drops before pops, both from the center to the sides
+    fn popout_moves(&self, board: &PopOutBoard<W, H, B, K>) -> Vec<PopOutMove> {
+        let drops = self
+            .column_order
+            .iter()
+            .filter(|&&colm| board.can_drop(colm))
+            .map(|&colm| PopOutMove::Drop(colm));
+        let pops = self
+            .column_order
+            .iter()
+            .filter(|&&colm| board.can_pop(colm))
+            .map(|&colm| PopOutMove::Pop(colm));
+        drops.chain(pops).collect()
+    }
+
     pub fn explored_nodes(&self) -> usize {
         self.explored_nodes
     }
 
+    // saves the table with the size of the board, to warm start later searches
+    pub fn save_table(&self, table_path: &str) -> Result<(), io::Error> {
+        self.table.save(table_path, [W, H, K])
+    }
+
+    // replaces the table, the engines which shared the previous one keep it
+    pub fn load_table(&mut self, table_path: &str) -> Result<(), io::Error> {
+        self.table = Arc::new(TranspositionTable::load(table_path, [W, H, K])?);
+        Ok(())
+    }
+
     pub fn reset(&mut self) {
         self.explored_nodes = 0;
-        self.table = TranspositionTable::new(TRANSPOSITION_TABLE_SIZE);
+        self.table = Arc::new(TranspositionTable::new(
+            self.table.size(),
+            self.table.key_bits(),
+            self.table.has_best_moves(),
+        ));
+        self.history.fill(0);
+        self.killers.fill([W; 2]);
+    }
+}
+
+/*
+    the transposition table is allocated by build, either with the given memory
+    or DEFAULT_TABLE_MEMORY_MB, unless an existing table is shared, the memory is
+    never exceeded as long keys take more key words per entry instead
+*/
+pub struct EngineBuilder<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    table_memory_mb: usize,
+    table: Option<Arc<TranspositionTable<B>>>,
+    book: OpeningBook<W, H, B, K>,
+    threads: usize,
+    history_heuristic: bool,
+    killer_moves: bool,
+    best_move_hints: bool,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default
+    for EngineBuilder<W, H, B, K>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> EngineBuilder<W, H, B, K> {
+    pub fn new() -> Self {
+        Self {
+            table_memory_mb: DEFAULT_TABLE_MEMORY_MB,
+            table: None,
+            book: OpeningBook::new(),
+            threads: 1,
+            history_heuristic: false,
+            killer_moves: false,
+            best_move_hints: false,
+        }
+    }
+
+    pub fn table_memory_mb(mut self, memory_mb: usize) -> Self {
+        self.table_memory_mb = memory_mb;
+        self
+    }
+
+    /*
+        replaces the memory budget, build panics if the table was made
+        for keys shorter than those of the board, see TranspositionTable::new
+    */
+    pub fn table(mut self, table: Arc<TranspositionTable<B>>) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    pub fn book(mut self, book: OpeningBook<W, H, B, K>) -> Self {
+        self.book = book;
+        self
+    }
+
+    // see Engine::set_threads, no speedup has been shown for more than 1 thread
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /*
+        moves which cut in other positions are searched before the others of the same
+        Board::score instead of the center columns first, off by default as it explores
+        more nodes on the 7x6 board
+    */
+    pub fn history_heuristic(mut self, enabled: bool) -> Self {
+        self.history_heuristic = enabled;
+        self
+    }
+
+    // same for the last two moves which cut after as many played moves
+    pub fn killer_moves(mut self, enabled: bool) -> Self {
+        self.killer_moves = enabled;
+        self
+    }
+
+    /*
+        the move which cut in a position is stored in the table and searched first
+        when the position comes back, off by default as the extra byte per entry
+        leaves room for fewer entries and more nodes are explored on the 7x6 board,
+        a shared table keeps its own setting
+    */
+    pub fn best_move_hints(mut self, enabled: bool) -> Self {
+        self.best_move_hints = enabled;
+        self
+    }
+
+    pub fn build(self) -> Engine<W, H, B, K> {
+        // the table keys are those of the board, so the board is checked at compile time
+        let () = Board::<W, H, B, K>::FITS_IN_BITBOARD;
+        let () = Board::<W, H, B, K>::VALID_CONNECT;
+        let mut column_order = [0; W];
+
+        // [3, 2, 4, 1, 5, 0, 6]
+        for ind in 0..(W as i32) {
+            let colm = W as i32 / 2 + (1 - 2 * (ind & 1)) * (ind + 1) / 2;
+            column_order[ind as usize] = colm as usize;
+        }
+
+        let table = self.table.unwrap_or_else(|| {
+            Arc::new(TranspositionTable::with_memory(
+                self.table_memory_mb,
+                W * (H + 1),
+                self.best_move_hints,
+            ))
+        });
+        // a shorter key would mistake the positions of the board for each other
+        assert!(
+            table.key_bits() >= W * (H + 1),
+            "the transposition table was made for a smaller board"
+        );
+
+        Engine {
+            column_order,
+            table,
+            book: self.book,
+            explored_nodes: 0,
+            budget: Budget::default(),
+            horizon_hits: 0,
+            table_lookups: 0,
+            table_hits: 0,
+            observer: None,
+            progress: None,
+            threads: self.threads,
+            history_heuristic: self.history_heuristic,
+            killer_moves: self.killer_moves,
+            history: vec![0; 2 * W * (H + 1)],
+            killers: vec![[W; 2]; W * H],
+        }
     }
 }
@@ -0,0 +1,145 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    bitboard::Bitboard,
+    board::{Board, CONNECT, HEIGHT, InvalidMoveErr, ParsingBoardErr, WIDTH, parse_colm},
+};
+
+/*
+    a game is the sequence of played columns together with a cursor,
+    moves[..ply] are on the board and moves[ply..] can be redone
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameRecord<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    board: Board<W, H, B, K>,
+    moves: Vec<usize>,
+    ply: usize,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> GameRecord<W, H, B, K> {
+    pub fn new() -> Self {
+        Self {
+            board: Board::new(),
+            moves: Vec::new(),
+            ply: 0,
+        }
+    }
+
+    // a game which continues from an arbitrary position, moves and plies are counted from it
+    pub fn from_position(board: Board<W, H, B, K>) -> Self {
+        Self {
+            board,
+            moves: Vec::new(),
+            ply: 0,
+        }
+    }
+
+    pub fn board(&self) -> Board<W, H, B, K> {
+        self.board
+    }
+
+    // the columns which are currently on the board
+    pub fn moves(&self) -> &[usize] {
+        &self.moves[..self.ply]
+    }
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    // number of recorded moves, including the ones which can be redone
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    // playing a new move discards the moves which could have been redone
+    pub fn play(&mut self, colm: usize) -> Result<(), InvalidMoveErr> {
+        if self.board.is_game_over() {
+            return Err(InvalidMoveErr::GameOver);
+        }
+        self.board.try_play(colm)?;
+        self.moves.truncate(self.ply);
+        self.moves.push(colm);
+        self.ply += 1;
+        Ok(())
+    }
+
+    // returns the column of the undone move
+    pub fn undo(&mut self) -> Option<usize> {
+        if self.ply == 0 {
+            return None;
+        }
+        self.ply -= 1;
+        let colm = self.moves[self.ply];
+        self.board.unplay(colm);
+        Some(colm)
+    }
+
+    // returns the column of the redone move
+    pub fn redo(&mut self) -> Option<usize> {
+        let colm = *self.moves.get(self.ply)?;
+        self.board.play(colm);
+        self.ply += 1;
+        Some(colm)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.ply > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.ply < self.moves.len()
+    }
+
+    // moves the cursor to any recorded ply, returns false if ply is past the end of the record
+    pub fn rewind(&mut self, ply: usize) -> bool {
+        if ply > self.moves.len() {
+            return false;
+        }
+        while self.ply > ply {
+            self.undo();
+        }
+        while self.ply < ply {
+            self.redo();
+        }
+        true
+    }
+}
+
+// the digit notation of the moves on the board, same as Board::from_str
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Display
+    for GameRecord<W, H, B, K>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &colm in self.moves() {
+            write!(f, "{}", colm + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// unlike Board::from_str, a finished game is accepted as long as no move follows the win
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> FromStr
+    for GameRecord<W, H, B, K>
+{
+    type Err = ParsingBoardErr;
+
+    fn from_str(encoded_game: &str) -> Result<Self, Self::Err> {
+        let mut game = Self::new();
+        for (index, ch) in encoded_game.chars().enumerate() {
+            let colm = parse_colm(index, ch)?;
+            game.play(colm)
+                .map_err(|err| ParsingBoardErr::from_move_err(index, err))?;
+        }
+        Ok(game)
+    }
+}
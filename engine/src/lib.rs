@@ -1,8 +1,13 @@
+pub mod bitboard;
 pub mod board;
 pub mod engine;
+pub mod game;
 mod move_sorter;
 pub mod opening_book;
-mod transposition_table;
+pub mod outcome;
+pub mod popout;
+pub mod progress;
+pub mod transposition_table;
 
 use paste::paste;
 
@@ -12,7 +17,7 @@ macro_rules! generate_eval_tests {
             $(
                 #[test]
                 fn [<score_test_case_ $encoded_board>]() {
-                    let mut engine = crate::engine::Engine::new();
+                    let mut engine: crate::engine::Engine = crate::engine::Engine::new();
                     let board = $encoded_board.parse().unwrap();
                     let score = engine.score(board);
 
@@ -233,7 +238,7 @@ generate_eval_tests! {
 
 #[test]
 fn test_empty_board() {
-    let mut engine = crate::engine::Engine::new();
+    let mut engine: crate::engine::Engine = crate::engine::Engine::new();
     let board = "4".parse().unwrap();
     let score = engine.score(board);
 
@@ -242,8 +247,910 @@ fn test_empty_board() {
 
 #[test]
 fn test_opening_book() {
-    let book = crate::opening_book::OpeningBook::open("../opening-book-8").unwrap();
+    let book: crate::opening_book::OpeningBook =
+        crate::opening_book::OpeningBook::open("../opening-book-8").unwrap();
     let board = "12344321".parse().unwrap();
     let score = book.score(&board);
     assert_eq!(score, Some(6));
 }
+
+#[test]
+fn test_board_play_api() {
+    use crate::board::{Board, InvalidMoveErr};
+
+    let mut board: Board = Board::new();
+    for _ in 0..6 {
+        board.try_play(0).unwrap();
+    }
+    assert_eq!(board.try_play(0), Err(InvalidMoveErr::ColumnFull(0)));
+    assert_eq!(board.try_play(7), Err(InvalidMoveErr::ColumnOutOfRange(7)));
+    assert_eq!(
+        board.legal_columns().collect::<Vec<_>>(),
+        vec![1, 2, 3, 4, 5, 6]
+    );
+
+    for (colm, child) in board.children() {
+        let encoded_board = format!("111111{}", colm + 1);
+        assert_eq!(child, encoded_board.parse().unwrap());
+        assert_eq!(child.played_moves(), 7);
+    }
+}
+
+#[test]
+fn test_game_status() {
+    use crate::board::{Board, GameStatus};
+
+    let mut board: Board = Board::new();
+    for colm in [3, 3, 4, 4, 5, 5] {
+        board.play(colm);
+    }
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    assert!(board.winning_line().is_empty());
+
+    board.play(6);
+    assert_eq!(board.status(), GameStatus::FirstPlayerWon);
+    assert_eq!(board.winning_line(), vec![(3, 0), (4, 0), (5, 0), (6, 0)]);
+
+    let mut board: Board = Board::new();
+    for colm in [0, 1, 0, 1, 0, 1, 2, 1] {
+        board.play(colm);
+    }
+    assert_eq!(board.status(), GameStatus::SecondPlayerWon);
+    assert_eq!(board.winning_line(), vec![(1, 0), (1, 1), (1, 2), (1, 3)]);
+}
+
+#[test]
+fn test_game_record() {
+    use crate::game::GameRecord;
+
+    let mut game: GameRecord = "4455667".parse().unwrap();
+    assert!(game.board().is_game_over());
+    assert!(game.play(0).is_err());
+
+    assert_eq!(game.undo(), Some(6));
+    assert_eq!(game.board(), "445566".parse().unwrap());
+    assert!(game.rewind(2));
+    assert_eq!(game.to_string(), "44");
+    assert_eq!(game.redo(), Some(4));
+    assert!(game.rewind(7));
+    assert_eq!(game.to_string(), "4455667");
+    assert!(!game.rewind(8));
+
+    game.rewind(0);
+    assert_eq!(game.board(), crate::board::Board::new());
+    game.play(0).unwrap();
+    assert_eq!(game.len(), 1);
+    assert!(!game.can_redo());
+    assert!("4455667".parse::<GameRecord>().is_ok());
+    assert!("44556677".parse::<GameRecord>().is_err());
+}
+
+#[test]
+fn test_parsing_errors() {
+    use crate::{
+        board::{Board, InvalidMoveErr, ParsingBoardErr},
+        game::GameRecord,
+    };
+
+    let err = "12a4".parse::<Board>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::InvalidCharacter { index: 2, ch: 'a' });
+    let err = "440".parse::<Board>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::ColumnOutOfRange { index: 2, colm: 0 });
+    let err = "8".parse::<Board>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::ColumnOutOfRange { index: 0, colm: 8 });
+    let err = "1111111".parse::<Board>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::ColumnFull { index: 6, colm: 1 });
+    let err = "4455667".parse::<Board>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::WinningMove { index: 6 });
+    let err = "44556671".parse::<GameRecord>().unwrap_err();
+    assert_eq!(err, ParsingBoardErr::GameAlreadyWon { index: 7 });
+    assert_eq!(err.to_string(), "The game was already won at ply 7");
+
+    let mut board: Board = Board::new();
+    let err = board.try_play(usize::MAX).unwrap_err();
+    assert_eq!(err, InvalidMoveErr::ColumnOutOfRange(usize::MAX));
+    assert_eq!(
+        err.to_string(),
+        format!("Column {} is outside of the board", usize::MAX)
+    );
+}
+
+#[test]
+fn test_canonical_key() {
+    use crate::board::Board;
+
+    let board: Board = "1122".parse().unwrap();
+    let mirrored: Board = "7766".parse().unwrap();
+    assert_eq!(board.mirrored_key(), mirrored.key());
+    assert_eq!(mirrored.mirrored_key(), board.key());
+    assert_eq!(board.canonical_key(), mirrored.canonical_key());
+    assert_ne!(board.key(), mirrored.key());
+
+    // a symmetric position is its own mirror
+    let board: Board = "4444".parse().unwrap();
+    assert_eq!(board.mirrored_key(), board.key());
+
+    let mut board = Board::<10, 8, u128>::new();
+    let mut mirrored = Board::<10, 8, u128>::new();
+    for colm in [0, 0, 2, 9] {
+        board.play(colm);
+        mirrored.play(9 - colm);
+    }
+    assert_eq!(board.mirrored_key(), mirrored.key());
+}
+
+#[test]
+fn test_analysis() {
+    use crate::{board::Board, engine::Engine};
+
+    let mut engine: Engine = Engine::new();
+    // the first player wins with their next move in column 3 or 7
+    let board: Board = "445566".parse().unwrap();
+    let analysis = engine.analyze(board).unwrap();
+    assert_eq!(analysis.best_moves, vec![2, 6]);
+    assert_eq!(analysis.score, 18);
+    assert_eq!(analysis.outcome, crate::outcome::Outcome::Win(1));
+    assert_eq!(analysis.plies_to_end, 1);
+    assert_eq!(analysis.principal_variation.len(), 1);
+
+    let board: Board = "2252576253462244111563365343671351441".parse().unwrap();
+    let analysis = engine.analyze(board).unwrap();
+    assert_eq!(analysis.score, engine.score(board));
+    assert_eq!(analysis.principal_variation.len(), analysis.plies_to_end);
+    let mut end = board;
+    for &colm in &analysis.principal_variation {
+        assert!(!end.is_game_over());
+        end.play(colm);
+    }
+    assert!(end.is_game_over());
+
+    assert!(
+        engine
+            .analyze(
+                "4455667"
+                    .parse::<crate::game::GameRecord>()
+                    .unwrap()
+                    .board()
+            )
+            .is_none()
+    );
+}
+
+#[test]
+fn test_game_over() {
+    use crate::{engine::Engine, game::GameRecord};
+
+    // the first player has already won, no move of the second one is scored
+    let board = "4455667".parse::<GameRecord>().unwrap().board();
+    let mut engine: Engine = Engine::new();
+    assert_eq!(engine.solve_scores(board), [None; 7]);
+    assert_eq!(engine.weak_solve_scores(board), [None; 7]);
+    assert_eq!(engine.solve(board), [None; 7]);
+    assert_eq!(engine.explored_nodes(), 0);
+}
+
+#[test]
+#[should_panic(expected = "the game is already over")]
+fn test_game_over_score() {
+    use crate::{engine::Engine, game::GameRecord};
+
+    let board = "4455667".parse::<GameRecord>().unwrap().board();
+    let mut engine: Engine = Engine::new();
+    engine.score(board);
+}
+
+#[test]
+fn test_outcome() {
+    use crate::{board::Board, engine::Engine, outcome::Outcome};
+
+    assert_eq!(Outcome::from_score(18, 36), Some(Outcome::Win(1)));
+    assert_eq!(Outcome::from_score(-1, 3), Some(Outcome::Loss(1)));
+    assert_eq!(Outcome::from_score(0, 42), Some(Outcome::Draw));
+    for remaining in 0..=42 {
+        for score in -21..=21 {
+            if let Some(outcome) = Outcome::from_score(score, remaining) {
+                assert!(outcome.plies(remaining) <= remaining);
+                assert_eq!(outcome.to_score(remaining), score);
+            }
+        }
+    }
+    // scores which need more moves than are left
+    assert_eq!(Outcome::from_score(19, 36), None);
+    assert_eq!(Outcome::from_score(-2, 3), None);
+    let board: Board = "4455".parse().unwrap();
+    assert_eq!(board.outcome(30), None);
+    assert_eq!(board.outcome(i32::MIN), None);
+    // a weak score is a sign, not the number of moves to the end
+    assert_eq!(board.outcome(1), Some(Outcome::Win(19)));
+    assert_eq!(board.outcome(-1), Some(Outcome::Loss(19)));
+    assert_eq!(Outcome::Win(3).to_string(), "wins in 3 of your moves");
+    assert_eq!(Outcome::Loss(2).to_string(), "loses in 2 moves");
+
+    let mut engine: Engine = Engine::new();
+    let board: Board = "4455".parse().unwrap();
+    let outcomes = engine.solve(board);
+    // three in a row with both ends open
+    assert_eq!(outcomes[2], Some(Outcome::Win(2)));
+    assert_eq!(outcomes[5], Some(Outcome::Win(2)));
+    let mut board = board;
+    board.play(2);
+    let outcomes = engine.solve(board);
+    assert!(
+        outcomes
+            .iter()
+            .all(|&outcome| outcome == Some(Outcome::Loss(1)))
+    );
+}
+
+#[test]
+fn test_limited_search() {
+    use crate::{
+        board::Board,
+        engine::{Engine, SearchLimits},
+    };
+    use std::time::{Duration, Instant};
+
+    let mut engine: Engine = Engine::new();
+    let board: Board = "2252576253462244111563365343671351441".parse().unwrap();
+    let result = engine.search(board, SearchLimits::default()).unwrap();
+    assert!(result.proven);
+    assert_eq!(result.score, engine.score(board));
+    assert_eq!(
+        engine.solve_scores(board)[result.best_move],
+        Some(result.score)
+    );
+
+    // far from solvable within the limits
+    let limits = SearchLimits {
+        time: Some(Duration::from_millis(100)),
+        ..SearchLimits::default()
+    };
+    let start = Instant::now();
+    let result = engine.search(Board::new(), limits).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(!result.proven);
+    assert!(result.depth > 0);
+
+    let limits = SearchLimits {
+        nodes: Some(10_000),
+        ..SearchLimits::default()
+    };
+    let nodes = engine.explored_nodes();
+    let result = engine.search(Board::new(), limits).unwrap();
+    assert!(engine.explored_nodes() - nodes < 10_000 + 1024);
+    assert!(!result.proven);
+}
+
+#[test]
+fn test_cancellation() {
+    use crate::{
+        board::Board,
+        engine::{CancellationToken, Cancelled, Engine, SearchLimits},
+    };
+    use std::{thread, time::Duration};
+
+    let mut engine: Engine = Engine::new();
+    let cancel = CancellationToken::new();
+    let result = thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(100));
+            cancel.cancel();
+        });
+        engine.try_solve(Board::new(), &cancel)
+    });
+    assert_eq!(result, Err(Cancelled));
+
+    // the aborted search left only proven entries in the table
+    let board: Board = "2252576253462244111563365343671351441".parse().unwrap();
+    let mut fresh: Engine = Engine::new();
+    assert_eq!(engine.solve_scores(board), fresh.solve_scores(board));
+    assert_eq!(
+        engine.try_score(board, &CancellationToken::new()),
+        Ok(fresh.score(board))
+    );
+
+    let limits = SearchLimits {
+        cancel: Some(cancel),
+        ..SearchLimits::default()
+    };
+    let result = engine.search(Board::new(), limits).unwrap();
+    assert!(!result.proven);
+}
+
+#[test]
+fn test_progress_observer() {
+    use crate::{
+        board::Board,
+        engine::{Engine, SearchLimits},
+        progress::SearchProgress,
+    };
+    use std::sync::{Arc, Mutex};
+
+    let observed = Arc::new(Mutex::new(Vec::<SearchProgress>::new()));
+    let mut engine: Engine = Engine::new();
+    let observer = observed.clone();
+    engine.set_observer(Some(Arc::new(move |progress: &SearchProgress| {
+        observer.lock().unwrap().push(*progress)
+    })));
+
+    let board: Board = "2252576253462244111563365343671351441".parse().unwrap();
+    engine.score(board);
+    let reports = std::mem::take(&mut *observed.lock().unwrap());
+    let last = reports.last().unwrap();
+    assert!(last.finished);
+    assert_eq!(last.nodes, engine.explored_nodes());
+    assert!((0.0..=1.0).contains(&last.table_hit_rate));
+    // every null window of the binary search is reported
+    assert!(
+        reports[..reports.len() - 1]
+            .iter()
+            .all(|progress| !progress.finished && progress.window.is_some())
+    );
+
+    engine.search(board, SearchLimits::default());
+    let reports = std::mem::take(&mut *observed.lock().unwrap());
+    assert!(reports.iter().any(|progress| progress.depth == Some(1)));
+}
+
+#[test]
+fn test_parallel_search() {
+    use crate::{board::Board, engine::Engine};
+
+    let mut engine: Engine = Engine::new();
+    engine.set_threads(4);
+    for (encoded_board, expected_score) in [
+        ("52677675164321472411331752454", 0),
+        ("67152117737262713366376314254", 6),
+        ("427566236745127177115664464254", 2),
+        ("3575316255751336464276636772271112", -3),
+    ] {
+        let board: Board = encoded_board.parse().unwrap();
+        assert_eq!(engine.score(board), expected_score);
+    }
+
+    // more helpers than columns search other move orders than the first ones
+    let board: Board = "3575316255751336464276636772271112".parse().unwrap();
+    let mut engine: Engine = Engine::builder().threads(9).build();
+    assert_eq!(engine.score(board), -3);
+}
+
+#[test]
+fn test_shared_table() {
+    use crate::{board::Board, engine::Engine};
+    use std::thread;
+
+    let mut engine: Engine = Engine::new();
+    let board: Board = "52677675164321472411331752454".parse().unwrap();
+    let scores = engine.solve_scores(board);
+    let nodes = engine.explored_nodes();
+
+    // a second engine on another thread finds the results in the table
+    let mut other: Engine = Engine::with_table(engine.table());
+    let (other_scores, other_nodes) = thread::spawn(move || {
+        let scores = other.solve_scores(board);
+        (scores, other.explored_nodes())
+    })
+    .join()
+    .unwrap();
+    assert_eq!(other_scores, scores);
+    assert!(other_nodes < nodes);
+}
+
+#[test]
+fn test_table_memory() {
+    use crate::{board::Board, engine::Engine, transposition_table::TranspositionTable};
+
+    assert_eq!(
+        TranspositionTable::<u64>::with_memory(64, 49, false).size(),
+        4 * 2796181
+    );
+    // the best moves take a byte per entry
+    assert_eq!(
+        TranspositionTable::<u64>::with_memory(64, 49, true).size(),
+        4 * 2396743
+    );
+    // small tables and long keys take more key words per entry instead of more memory
+    assert_eq!(
+        TranspositionTable::<u64>::with_memory(1, 49, false).size(),
+        4 * 26209
+    );
+    assert_eq!(
+        TranspositionTable::<u64>::with_memory(64, 64, false).size(),
+        4 * 1677721
+    );
+
+    // larger boards keep the memory budget, with 2 key words (10 bytes) per entry in 64 MB
+    assert_eq!(Engine::<8, 7>::new().table().size(), 4 * 1677721);
+    assert_eq!(Engine::<7, 7>::new().table().size(), 4 * 1677721);
+    // and 3 key words (14 bytes) for the 90 bit keys of the 10x8 board in 16 MB
+    let engine = Engine::<10, 8, u128>::builder().table_memory_mb(16).build();
+    assert_eq!(engine.table().size(), 4 * 299569);
+
+    // a small table is slower but gives the same scores
+    let mut engine: Engine = Engine::builder().table_memory_mb(1).build();
+    assert_eq!(engine.table().size(), 4 * 26209);
+    let board: Board = "3575316255751336464276636772271112".parse().unwrap();
+    assert_eq!(engine.score(board), -3);
+    engine.reset();
+    assert_eq!(engine.table().size(), 4 * 26209);
+
+    // an even number of buckets does not tell the high bits of the keys apart
+    let table = TranspositionTable::new(4 << 18, 49, false);
+    let mut engine: Engine = Engine::with_table(std::sync::Arc::new(table));
+    let board: Board = "13234664".parse().unwrap();
+    assert_eq!(
+        engine.solve_scores(board),
+        [-5, -5, -3, -4, -16, -13, -12].map(Some)
+    );
+}
+
+#[test]
+fn test_long_keys() {
+    use crate::{bitboard::Bitboard, board::Board, engine::Engine};
+
+    // minimax without any table
+    fn naive_score<const W: usize, const H: usize, B: Bitboard>(board: Board<W, H, B>) -> i32 {
+        (0..W)
+            .filter(|&colm| board.can_play(colm))
+            .map(|colm| naive_move_score(board, colm))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn naive_move_score<const W: usize, const H: usize, B: Bitboard>(
+        board: Board<W, H, B>,
+        colm: usize,
+    ) -> i32 {
+        if board.is_winning(colm) {
+            return (W * H + 1 - board.played_moves()) as i32 / 2;
+        }
+        let mut child = board;
+        child.play(colm);
+        -naive_score(child)
+    }
+
+    /*
+        the 64 bit keys of the 8x7 board share a single bucket, and the positions after
+        this one the low 32 bits of their keys, which hold the full left half
+    */
+    let board = Board::<8, 7>::from_grid(
+        "O X O O . . . .
+         O X O X . . . .
+         O X O X . . . .
+         X O X O O O X O
+         X O O X O X O X
+         O X X X O X X O
+         X X O O X X O X",
+    )
+    .unwrap();
+    let mut engine = Engine::<8, 7>::builder().table_memory_mb(0).build();
+    assert_eq!(engine.table().size(), 4);
+    assert_eq!(engine.score(board), naive_score(board));
+    let scores = engine.solve_scores(board);
+    for colm in board.legal_columns() {
+        assert_eq!(scores[colm], Some(naive_move_score(board, colm)));
+    }
+
+    /*
+        the 90 bit keys of the 10x8 board only differ in the last two columns
+        after this one, so above their low 64 bits
+    */
+    let board = Board::<10, 8, u128>::from_grid(
+        "X X O X O O O X . .
+         O O O X X O X O . .
+         O O X O X X X O . .
+         O O X X O O O X . .
+         X X X O O X X X O O
+         O O O X X O O O X O
+         X X X O O X X X O O
+         X X X O X X X O O X",
+    )
+    .unwrap();
+    let mut engine = Engine::<10, 8, u128>::builder().table_memory_mb(0).build();
+    assert_eq!(engine.score(board), naive_score(board));
+    let scores = engine.solve_scores(board);
+    for colm in board.legal_columns() {
+        assert_eq!(scores[colm], Some(naive_move_score(board, colm)));
+    }
+}
+
+#[test]
+fn test_table_file() {
+    use crate::{board::Board, engine::Engine};
+    use std::io::ErrorKind;
+
+    let table_path = std::env::temp_dir().join("connect4-test-table");
+    let table_path = table_path.to_str().unwrap();
+
+    let mut engine: Engine = Engine::builder().table_memory_mb(4).build();
+    let board: Board = "52677675164321472411331752454".parse().unwrap();
+    let scores = engine.solve_scores(board);
+    let nodes = engine.explored_nodes();
+    engine.save_table(table_path).unwrap();
+
+    let mut warm: Engine = Engine::new();
+    warm.load_table(table_path).unwrap();
+    assert_eq!(warm.table().size(), engine.table().size());
+    assert_eq!(warm.solve_scores(board), scores);
+    assert!(warm.explored_nodes() < nodes);
+
+    let mut other = Engine::<6, 7>::new();
+    let err = other.load_table(table_path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // a size which does not match the file is rejected before allocating the table
+    let mut bytes = std::fs::read(table_path).unwrap();
+    bytes[11..19].copy_from_slice(&(1u64 << 60).to_le_bytes());
+    std::fs::write(table_path, bytes).unwrap();
+    let err = warm.load_table(table_path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    std::fs::remove_file(table_path).unwrap();
+}
+
+#[test]
+fn test_move_ordering() {
+    use crate::{board::Board, engine::Engine};
+
+    // the ordering changes the explored nodes, never the scores
+    let mut engine: Engine = Engine::new();
+    let mut ordered: Engine = Engine::builder()
+        .history_heuristic(true)
+        .killer_moves(true)
+        .build();
+    for moves in [
+        "3575316255751336464276636772271112",
+        "52677675164321472411331752454",
+    ] {
+        let board: Board = moves.parse().unwrap();
+        assert_eq!(ordered.solve_scores(board), engine.solve_scores(board));
+    }
+
+    // the best move is stored for the canonical position and read back for either mirror
+    let board: Board = "2252576253462244111563365343671351441".parse().unwrap();
+    let mirrored: Board = "6636312635426644777325523545217537447".parse().unwrap();
+    assert_eq!(mirrored.mirrored_key(), board.key());
+    let key = board.canonical_key();
+    let hinted: Engine = Engine::builder().best_move_hints(true).build();
+    for (stored, read, colm) in [(board, mirrored, 1), (mirrored, board, 4)] {
+        hinted.store_bound(&stored, key, 0, colm, true);
+        let (_, best_move) = hinted.table().get(key).unwrap();
+        assert_eq!(Engine::hint(&stored, key, best_move), Some(colm));
+        assert_eq!(Engine::hint(&read, key, best_move), Some(6 - colm));
+    }
+
+    // without best moves the table takes a byte less per entry and gives no hint
+    assert!(!engine.table().has_best_moves());
+    assert!(engine.table().size() > hinted.table().size());
+    engine.store_bound(&board, key, 0, 1, true);
+    let (_, best_move) = engine.table().get(key).unwrap();
+    assert_eq!(Engine::hint(&board, key, best_move), None);
+
+    let mut hinted: Engine = Engine::builder().best_move_hints(true).build();
+    let board: Board = "52677675164321472411331752454".parse().unwrap();
+    assert_eq!(hinted.solve_scores(board), engine.solve_scores(board));
+}
+
+#[test]
+fn test_weak_score() {
+    use crate::{
+        board::Board,
+        engine::Engine,
+        opening_book::{OpeningBook, WEAK_BOOK_KEY},
+    };
+
+    for moves in [
+        "4737",
+        "52677675164321472411331752454",
+        "3575316255751336464276636772271112",
+        "233377345754465174223731671122611552",
+    ] {
+        let board: Board = moves.parse().unwrap();
+        let mut engine: Engine = Engine::new();
+        assert_eq!(
+            engine.weak_score(board),
+            engine.score(board).signum(),
+            "Failed board: {moves}"
+        );
+    }
+
+    // a single window is often cheaper than the binary search in the opening, not always ("2145")
+    let board: Board = "13234664".parse().unwrap();
+    let mut engine: Engine = Engine::new();
+    assert_eq!(engine.score(board), -3);
+    let nodes = engine.explored_nodes();
+    let mut weak: Engine = Engine::new();
+    assert_eq!(weak.weak_score(board), -1);
+    assert!(weak.explored_nodes() < nodes);
+
+    let board: Board = "52677675164321472411331752454".parse().unwrap();
+    let scores = engine.solve_scores(board);
+    assert_eq!(
+        weak.weak_solve_scores(board),
+        scores.map(|score| score.map(i32::signum))
+    );
+
+    // the marked book only serves the weak searches
+    let book_path = std::env::temp_dir().join("connect4-test-weak-book");
+    let book_path = book_path.to_str().unwrap();
+    let board: Board = "13234664".parse().unwrap();
+    let mut bytes = Vec::new();
+    for (key, score) in [(WEAK_BOOK_KEY, 0), (board.canonical_key(), -1)] {
+        bytes.extend_from_slice(&key.to_le_bytes()[0..7]);
+        bytes.push((score + 127) as u8);
+    }
+    std::fs::write(book_path, bytes).unwrap();
+    let book = OpeningBook::open(book_path).unwrap();
+    assert!(book.is_weak());
+    let mut engine: Engine = Engine::with_book(book);
+    assert_eq!(engine.weak_score(board), -1);
+    assert_eq!(engine.explored_nodes(), 0);
+    assert_eq!(engine.score(board), -3);
+    std::fs::remove_file(book_path).unwrap();
+}
+
+#[test]
+fn test_small_board() {
+    let mut engine = crate::engine::Engine::<4, 4>::new();
+    let score = engine.score(crate::board::Board::new());
+
+    // 4x4 is a draw
+    assert_eq!(score, 0);
+}
+
+#[test]
+fn test_wide_board() {
+    use crate::board::{Board, GameStatus};
+
+    let mut board = Board::<10, 8, u128>::new();
+    for colm in [6, 6, 7, 7, 8, 8, 9] {
+        assert!(!board.is_game_over());
+        board.play(colm);
+    }
+    assert_eq!(board.status(), GameStatus::FirstPlayerWon);
+    assert_eq!(board.winning_line(), vec![(6, 0), (7, 0), (8, 0), (9, 0)]);
+
+    let mut engine = crate::engine::Engine::<4, 4, u128>::new();
+    assert_eq!(engine.score(Board::new()), 0);
+}
+
+#[test]
+fn test_connect_n_against_naive_scanner() {
+    use crate::board::{Board, GameStatus};
+
+    // counts the aligned stones through (colm, row) in every direction
+    fn naive_is_win<const W: usize, const H: usize>(
+        grid: &[[u8; H]; W],
+        colm: usize,
+        row: usize,
+        connect: usize,
+    ) -> bool {
+        let player = grid[colm][row];
+        [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dc, dr)| {
+            let mut count = 1;
+            for sign in [1, -1] {
+                let (mut c, mut r) = (colm as i32 + sign * dc, row as i32 + sign * dr);
+                while (0..W as i32).contains(&c)
+                    && (0..H as i32).contains(&r)
+                    && grid[c as usize][r as usize] == player
+                {
+                    count += 1;
+                    c += sign * dc;
+                    r += sign * dr;
+                }
+            }
+            count >= connect
+        })
+    }
+
+    fn check<const W: usize, const H: usize, const K: usize>(games: usize) {
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for _ in 0..games {
+            let mut board = Board::<W, H, u64, K>::new();
+            let mut grid = [[0u8; H]; W];
+            let mut heights = [0; W];
+
+            loop {
+                let player = 1 + (board.played_moves() % 2) as u8;
+                for colm in 0..W {
+                    let mut expected = false;
+                    if heights[colm] < H {
+                        grid[colm][heights[colm]] = player;
+                        expected = naive_is_win(&grid, colm, heights[colm], K);
+                        grid[colm][heights[colm]] = 0;
+                    }
+                    assert_eq!(board.is_winning(colm), expected, "{board:?} {colm}");
+                }
+
+                let legal: Vec<usize> = board.legal_columns().collect();
+                if legal.is_empty() {
+                    assert_eq!(board.status(), GameStatus::Draw);
+                    break;
+                }
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let colm = legal[(seed >> 33) as usize % legal.len()];
+
+                grid[colm][heights[colm]] = player;
+                let won = naive_is_win(&grid, colm, heights[colm], K);
+                heights[colm] += 1;
+                board.play(colm);
+
+                assert_eq!(board.is_game_over(), won || board.played_moves() == W * H);
+                if won {
+                    break;
+                }
+            }
+        }
+    }
+
+    check::<7, 6, 4>(500);
+    check::<7, 6, 3>(500);
+    check::<7, 6, 5>(500);
+    check::<8, 7, 6>(200);
+    check::<4, 4, 3>(200);
+}
+
+#[test]
+fn test_connect_three() {
+    use crate::board::Board;
+
+    // the first player wins connect 3 on 4x4 with their fifth stone
+    let mut engine = crate::engine::Engine::<4, 4, u64, 3>::new();
+    assert_eq!(engine.score(Board::new()), 4);
+}
+
+#[test]
+fn test_popout() {
+    use crate::board::GameStatus;
+    use crate::popout::{PopOutGame, PopOutMove};
+
+    // popping an own stone and popping back leads to the same position
+    let mut game: PopOutGame = PopOutGame::new();
+    for round in 1..=2 {
+        for mov in [0, 1].map(PopOutMove::Drop) {
+            game.play(mov).unwrap();
+        }
+        assert!(!game.board().can_pop(1));
+        for mov in [0, 1].map(PopOutMove::Pop) {
+            game.play(mov).unwrap();
+        }
+        assert!(
+            game.board()
+                .is_same_position(&PopOutGame::<7, 6>::new().board())
+        );
+        // the empty board occurs for the third time after the second round
+        let expected = if round == 2 {
+            GameStatus::Draw
+        } else {
+            GameStatus::Ongoing
+        };
+        assert_eq!(game.status(), expected);
+    }
+
+    /*
+        . . . .
+        X . . .
+        O X X X
+        X O O O   X pops column 1 and completes lines for both players
+    */
+    let mut game: PopOutGame = PopOutGame::new();
+    for colm in [0, 0, 0, 1, 1, 2, 2, 3, 3, 6] {
+        game.play(PopOutMove::Drop(colm)).unwrap();
+    }
+    assert_eq!(game.status(), GameStatus::Ongoing);
+
+    let mut engine: crate::engine::Engine = crate::engine::Engine::new();
+    assert_eq!(engine.score_popout(&game, 2), 2);
+    assert!(
+        engine
+            .solve_popout(&game, 2)
+            .contains(&(PopOutMove::Pop(0), 2))
+    );
+
+    game.play(PopOutMove::Pop(0)).unwrap();
+    assert_eq!(game.status(), GameStatus::FirstPlayerWon);
+}
+
+#[test]
+fn test_grid_format() {
+    use crate::board::{Board, InvalidPositionErr, ParsingGridErr};
+
+    let board: Board = "4453".parse().unwrap();
+    let grid = "\
+. . . . . . .
+. . . . . . .
+. . . . . . .
+. . . . . . .
+. . . O . . .
+. . O X X . .
+1 2 3 4 5 6 7";
+    assert_eq!(board.to_string(), grid);
+    assert_eq!(Board::from_grid(grid).unwrap(), board);
+
+    for encoded_board in [
+        "",
+        "2252576253462244111563365343671351441",
+        "67152117737262713366376314254",
+    ] {
+        let board: Board = encoded_board.parse().unwrap();
+        assert_eq!(Board::from_grid(&board.to_string()).unwrap(), board);
+    }
+
+    let floating = grid.replace(". . . O . . .", ". . . O . . X");
+    assert_eq!(
+        Board::<7, 6>::from_grid(&floating),
+        Err(ParsingGridErr::InvalidPosition(
+            InvalidPositionErr::FloatingStone { colm: 6, row: 1 }
+        ))
+    );
+    let count = grid.replace(". . O X X . .", ". . O O X . .");
+    assert_eq!(
+        Board::<7, 6>::from_grid(&count),
+        Err(ParsingGridErr::InvalidPosition(
+            InvalidPositionErr::StoneCount {
+                first: 1,
+                second: 3
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_position_validation() {
+    use crate::board::{Board, Cell, GameStatus, InvalidPositionErr};
+
+    let mut cells = [[Cell::Empty; 6]; 7];
+    for column in &mut cells[0..4] {
+        column[0] = Cell::FirstPlayer;
+    }
+    for column in &mut cells[0..3] {
+        column[1] = Cell::SecondPlayer;
+    }
+    let board = Board::<7, 6>::from_cells(&cells).unwrap();
+    assert_eq!(board.status(), GameStatus::FirstPlayerWon);
+    assert_eq!(board.cell(1, 1), Cell::SecondPlayer);
+    for (colm, row) in [(10, 0), (0, 6), (usize::MAX, usize::MAX)] {
+        assert_eq!(board.cell(colm, row), Cell::Empty);
+    }
+
+    // X is to move but already has a line
+    cells[5][0] = Cell::SecondPlayer;
+    assert_eq!(
+        Board::<7, 6>::from_cells(&cells),
+        Err(InvalidPositionErr::ConnectedBeforeLastMove)
+    );
+
+    cells[5][0] = Cell::Empty;
+    cells[3][1] = Cell::SecondPlayer;
+    assert_eq!(
+        Board::<7, 6>::from_cells(&cells),
+        Err(InvalidPositionErr::BothPlayersConnected)
+    );
+
+    let board: Board = "4455".parse().unwrap();
+    let (current, mask) = board.bitboards();
+    assert_eq!(Board::from_bitboards(current, mask), Ok(board));
+
+    let mut game =
+        crate::game::GameRecord::from_position(Board::from_bitboards(current, mask).unwrap());
+    game.play(0).unwrap();
+    game.undo();
+    assert_eq!(game.board(), board);
+    assert_eq!(game.ply(), 0);
+    assert_eq!(
+        Board::<7, 6>::from_bitboards(0, 1 | 1 << 7),
+        Err(InvalidPositionErr::StoneCount {
+            first: 0,
+            second: 2
+        })
+    );
+    assert_eq!(
+        Board::<7, 6>::from_bitboards(1 << 1, 1 << 1),
+        Err(InvalidPositionErr::FloatingStone { colm: 0, row: 1 })
+    );
+    assert_eq!(
+        Board::<7, 6>::from_bitboards(1 << 6, 1 << 6),
+        Err(InvalidPositionErr::OutsideOfBoard)
+    );
+}
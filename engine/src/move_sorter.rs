@@ -1,45 +1,50 @@
-use crate::board::WIDTH;
+use crate::bitboard::Bitboard;
 
-pub struct MoveSorter {
-    entries: [Entry; WIDTH],
+pub struct MoveSorter<const W: usize, B: Bitboard> {
+    entries: [Entry<B>; W],
     size: usize,
 }
 
 #[derive(Clone, Copy, Default)]
-struct Entry {
-    mov: u64,
+struct Entry<B: Bitboard> {
+    mov: B,
+    colm: usize,
     score: i32,
 }
 
-impl MoveSorter {
+impl<const W: usize, B: Bitboard> MoveSorter<W, B> {
     pub fn new() -> Self {
         Self {
-            entries: [Entry::default(); WIDTH],
+            entries: [Entry::default(); W],
             size: 0,
         }
     }
 
-    pub fn add(&mut self, mov: u64, score: i32) {
+    // moves of equal score come out in the reverse order of their addition
+    pub fn add(&mut self, mov: B, colm: usize, score: i32) {
         let mut pos = self.size;
         while pos != 0 && self.entries[pos - 1].score > score {
-            self.entries[pos] = self.entries[pos-1];
+            self.entries[pos] = self.entries[pos - 1];
             pos -= 1;
         }
         self.entries[pos].mov = mov;
+        self.entries[pos].colm = colm;
         self.entries[pos].score = score;
         self.size += 1;
     }
 }
 
-impl Iterator for MoveSorter {
-    type Item = u64;
+impl<const W: usize, B: Bitboard> Iterator for MoveSorter<W, B> {
+    // the move and its column
+    type Item = (B, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.size > 0 {
             self.size -= 1;
-            Some(self.entries[self.size].mov)
+            let entry = self.entries[self.size];
+            Some((entry.mov, entry.colm))
         } else {
             None
         }
     }
-}
\ No newline at end of file
+}
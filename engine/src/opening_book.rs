@@ -2,44 +2,92 @@ use std::{
     collections::HashMap,
     fs::File,
     io::{BufReader, Error, Read},
+    marker::PhantomData,
 };
 
-use crate::board::Board;
+use crate::{
+    bitboard::Bitboard,
+    board::{Board, CONNECT, HEIGHT, WIDTH},
+};
 
 const SCORE_SHIFT: u8 = 127;
+/*
+    a weak book, whose scores are only 1, 0 or -1, holds a record with this key,
+    which no board has as current + mask never sets every bit of a column
+*/
+pub const WEAK_BOOK_KEY: u64 = (1 << 56) - 1;
 
 #[derive(Clone)]
-pub struct OpeningBook {
+pub struct OpeningBook<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
     book: HashMap<u64, i32>,
+    weak: bool,
+    _board: PhantomData<Board<W, H, B, K>>,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default
+    for OpeningBook<W, H, B, K>
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl OpeningBook {
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> OpeningBook<W, H, B, K> {
+    // keys are stored on 7 bytes in the book file
+    const FITS_IN_KEY: () = assert!(W * (H + 1) <= 56, "the board keys do not fit in 7 bytes");
+
     pub fn new() -> Self {
         Self {
             book: HashMap::new(),
+            weak: false,
+            _board: PhantomData,
         }
     }
 
     pub fn open(book_path: &str) -> Result<Self, Error> {
+        let () = Self::FITS_IN_KEY;
         let file = File::open(book_path)?;
         let mut reader = BufReader::new(file);
 
         let mut book = HashMap::new();
+        let mut weak = false;
         let mut buf = [0u8; 8];
 
-        while let Ok(_) = reader.read_exact(&mut buf) {
+        while reader.read_exact(&mut buf).is_ok() {
             let mut key_bytes = [0u8; 8];
             key_bytes[0..7].copy_from_slice(&buf[0..7]);
             let key = u64::from_le_bytes(key_bytes);
+            if key == WEAK_BOOK_KEY {
+                weak = true;
+                continue;
+            }
 
             let score = buf[7] as i32 - SCORE_SHIFT as i32;
             book.insert(key, score);
         }
 
-        Ok(Self { book })
+        Ok(Self {
+            book,
+            weak,
+            _board: PhantomData,
+        })
+    }
+
+    /*
+        books store canonical keys, older books which store every position
+        still contain the canonical one of each mirrored pair
+    */
+    pub fn score(&self, board: &Board<W, H, B, K>) -> Option<i32> {
+        self.book.get(&board.canonical_key().low_u64()).cloned()
     }
 
-    pub fn score(&self, board: &Board) -> Option<i32> {
-        self.book.get(&board.key()).cloned()
+    // the scores only tell wins, draws and losses apart
+    pub fn is_weak(&self) -> bool {
+        self.weak
     }
 }
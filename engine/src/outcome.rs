@@ -0,0 +1,66 @@
+use std::fmt::Display;
+
+/*
+    readable form of an engine score, always from the point of view of the player to move,
+    the counts are the moves of the player who wins, including the winning one
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    // the player to move wins with their n-th move
+    Win(usize),
+    Draw,
+    // the opponent wins with their n-th move
+    Loss(usize),
+}
+
+impl Outcome {
+    /*
+        a score s > 0 is a win with the ceil(remaining / 2) + 1 - s-th move,
+        a score s < 0 a loss with the opponent's remaining / 2 + 1 + s-th move,
+        remaining being the number of empty cells, None for the scores which need
+        more moves than are left, the weak scores are only signs and read as the
+        last possible win or loss
+    */
+    pub fn from_score(score: i32, remaining: usize) -> Option<Self> {
+        let rounds = score.unsigned_abs() as usize;
+        if score > 0 {
+            let max_rounds = remaining.div_ceil(2);
+            (rounds <= max_rounds).then(|| Outcome::Win(max_rounds + 1 - rounds))
+        } else if score < 0 {
+            let max_rounds = remaining / 2;
+            (rounds <= max_rounds).then(|| Outcome::Loss(max_rounds + 1 - rounds))
+        } else {
+            Some(Outcome::Draw)
+        }
+    }
+
+    // inverse of from_score
+    pub fn to_score(self, remaining: usize) -> i32 {
+        match self {
+            Outcome::Win(moves) => (remaining.div_ceil(2) + 1 - moves) as i32,
+            Outcome::Draw => 0,
+            Outcome::Loss(moves) => -((remaining / 2 + 1 - moves) as i32),
+        }
+    }
+
+    // plies until the end of the game, a draw fills the remaining cells
+    pub fn plies(self, remaining: usize) -> usize {
+        match self {
+            Outcome::Win(moves) => 2 * moves - 1,
+            Outcome::Draw => remaining,
+            Outcome::Loss(moves) => 2 * moves,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Win(1) => write!(f, "wins with your next move"),
+            Outcome::Win(moves) => write!(f, "wins in {moves} of your moves"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss(1) => write!(f, "loses to the next move"),
+            Outcome::Loss(moves) => write!(f, "loses in {moves} moves"),
+        }
+    }
+}
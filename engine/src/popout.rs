@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+use crate::{
+    bitboard::Bitboard,
+    board::{
+        Board, CONNECT, GameStatus, HEIGHT, InvalidMoveErr, WIDTH, bottom_mask_colm, column_mask,
+        compute_alignment, top_mask_colm,
+    },
+};
+
+// number of times a position has to occur for the game to be drawn
+const REPETITION_LIMIT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PopOutMove {
+    // a stone is dropped on top of the column
+    Drop(usize),
+    // the own stone at the bottom of the column is removed and the column falls down
+    Pop(usize),
+}
+
+impl Display for PopOutMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PopOutMove::Drop(colm) => write!(f, "{}", colm + 1),
+            PopOutMove::Pop(colm) => write!(f, "p{}", colm + 1),
+        }
+    }
+}
+
+/*
+    same encoding as Board, the stones of a column stay contiguous from the bottom
+    because a pop shifts the whole column down,
+    played_moves counts drops and pops so its parity still gives the player to move
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PopOutBoard<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    current: B,
+    mask: B,
+    played_moves: usize,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default
+    for PopOutBoard<W, H, B, K>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> PopOutBoard<W, H, B, K> {
+    pub fn new() -> Self {
+        let () = Board::<W, H, B, K>::FITS_IN_BITBOARD;
+        let () = Board::<W, H, B, K>::VALID_CONNECT;
+        Self {
+            current: B::ZERO,
+            mask: B::ZERO,
+            played_moves: 0,
+        }
+    }
+
+    pub fn can_drop(&self, colm: usize) -> bool {
+        colm < W && (self.mask & top_mask_colm::<B, H>(colm)) == B::ZERO
+    }
+
+    pub fn can_pop(&self, colm: usize) -> bool {
+        colm < W && (self.current & bottom_mask_colm::<B, H>(colm)) != B::ZERO
+    }
+
+    // drops first, both from left to right
+    pub fn legal_moves(&self) -> Vec<PopOutMove> {
+        let drops = (0..W)
+            .filter(|&colm| self.can_drop(colm))
+            .map(PopOutMove::Drop);
+        let pops = (0..W)
+            .filter(|&colm| self.can_pop(colm))
+            .map(PopOutMove::Pop);
+        drops.chain(pops).collect()
+    }
+
+    // the caller must make sure that the move is legal
+    pub fn play(&mut self, mov: PopOutMove) {
+        match mov {
+            PopOutMove::Drop(colm) => {
+                self.current ^= self.mask;
+                self.mask |= self.mask + bottom_mask_colm::<B, H>(colm);
+            }
+            PopOutMove::Pop(colm) => {
+                let colm_mask = column_mask::<B, H>(colm);
+                let bottom = bottom_mask_colm::<B, H>(colm);
+                let mask = ((self.mask & colm_mask) ^ bottom) >> 1;
+                let current = ((self.current & colm_mask) ^ bottom) >> 1;
+                self.mask = (self.mask & !colm_mask) | mask;
+                self.current = (self.current & !colm_mask) | current;
+                // the opponent is to move
+                self.current ^= self.mask;
+            }
+        }
+        self.played_moves += 1;
+    }
+
+    // checked version of play
+    pub fn try_play(&mut self, mov: PopOutMove) -> Result<(), InvalidMoveErr> {
+        if self.is_game_over() {
+            return Err(InvalidMoveErr::GameOver);
+        }
+        match mov {
+            PopOutMove::Drop(colm) | PopOutMove::Pop(colm) if colm >= W => {
+                return Err(InvalidMoveErr::ColumnOutOfRange(colm));
+            }
+            PopOutMove::Drop(colm) if !self.can_drop(colm) => {
+                return Err(InvalidMoveErr::ColumnFull(colm));
+            }
+            PopOutMove::Pop(colm) if !self.can_pop(colm) => {
+                return Err(InvalidMoveErr::CannotPop(colm));
+            }
+            _ => {}
+        }
+        self.play(mov);
+        Ok(())
+    }
+
+    // number of drops and pops since the empty board
+    pub fn played_moves(&self) -> usize {
+        self.played_moves
+    }
+
+    pub fn key(&self) -> B {
+        self.current + self.mask
+    }
+
+    /*
+        a pop can complete lines for both players at once,
+        in that case the player who popped wins
+    */
+    pub fn status(&self) -> GameStatus {
+        if compute_alignment::<B, H, K>(self.current ^ self.mask) != B::ZERO {
+            return self.last_player_won();
+        }
+        if compute_alignment::<B, H, K>(self.current) != B::ZERO {
+            return match self.last_player_won() {
+                GameStatus::FirstPlayerWon => GameStatus::SecondPlayerWon,
+                _ => GameStatus::FirstPlayerWon,
+            };
+        }
+        // a full board where the current player has no stone at the bottom
+        if self.mask == Board::<W, H, B, K>::board_mask()
+            && (self.current & Board::<W, H, B, K>::bottom_mask()) == B::ZERO
+        {
+            GameStatus::Draw
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.status() != GameStatus::Ongoing
+    }
+
+    // same stones and same player to move, regardless of how many moves were played
+    pub fn is_same_position(&self, other: &Self) -> bool {
+        self.current == other.current
+            && self.mask == other.mask
+            && self.played_moves % 2 == other.played_moves % 2
+    }
+
+    fn last_player_won(&self) -> GameStatus {
+        if self.played_moves % 2 == 1 {
+            GameStatus::FirstPlayerWon
+        } else {
+            GameStatus::SecondPlayerWon
+        }
+    }
+}
+
+/*
+    a PopOut game with its history, because a position which occurs
+    for the third time ends the game in a draw
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopOutGame<
+    const W: usize = WIDTH,
+    const H: usize = HEIGHT,
+    B: Bitboard = u64,
+    const K: usize = CONNECT,
+> {
+    history: Vec<PopOutBoard<W, H, B, K>>,
+    moves: Vec<PopOutMove>,
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> Default
+    for PopOutGame<W, H, B, K>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize, B: Bitboard, const K: usize> PopOutGame<W, H, B, K> {
+    pub fn new() -> Self {
+        Self {
+            history: vec![PopOutBoard::new()],
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn board(&self) -> PopOutBoard<W, H, B, K> {
+        *self.history.last().unwrap()
+    }
+
+    pub fn moves(&self) -> &[PopOutMove] {
+        &self.moves
+    }
+
+    // previous positions of the game, used to detect repetitions in a search
+    pub fn history(&self) -> &[PopOutBoard<W, H, B, K>] {
+        &self.history
+    }
+
+    pub fn play(&mut self, mov: PopOutMove) -> Result<(), InvalidMoveErr> {
+        if self.status() != GameStatus::Ongoing {
+            return Err(InvalidMoveErr::GameOver);
+        }
+        let mut board = self.board();
+        board.try_play(mov)?;
+        self.history.push(board);
+        self.moves.push(mov);
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Option<PopOutMove> {
+        let mov = self.moves.pop()?;
+        self.history.pop();
+        Some(mov)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        let board = self.board();
+        let status = board.status();
+        if status != GameStatus::Ongoing {
+            return status;
+        }
+
+        let occurrences = self
+            .history
+            .iter()
+            .filter(|prev| prev.is_same_position(&board))
+            .count();
+        if occurrences >= REPETITION_LIMIT {
+            GameStatus::Draw
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/*
+    snapshot of a running search, the counters start with the call to
+    Engine::score, solve, search or one of their try_ versions
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchProgress {
+    // null window of the binary search in score, None in search
+    pub window: Option<(i32, i32)>,
+    // iteration of search, None in score and solve
+    pub depth: Option<usize>,
+    pub nodes: usize,
+    pub nodes_per_second: f64,
+    // share of the positions found in the transposition table, 0 before the first lookup
+    pub table_hit_rate: f64,
+    pub elapsed: Duration,
+    // the last report of a search, sent once it returns
+    pub finished: bool,
+}
+
+/*
+    called from the searching thread when a null window or a depth starts,
+    about every PROGRESS_INTERVAL in between and once at the end,
+    closures taking a &SearchProgress are observers
+*/
+pub trait SearchObserver: Send + Sync {
+    fn on_progress(&self, progress: &SearchProgress);
+}
+
+impl<F: Fn(&SearchProgress) + Send + Sync> SearchObserver for F {
+    fn on_progress(&self, progress: &SearchProgress) {
+        self(progress)
+    }
+}
@@ -1,50 +1,317 @@
-#[derive(Clone)]
-pub struct TranspositionTable {
-    table: Vec<Entry>
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Write},
+    marker::PhantomData,
+    sync::atomic::{AtomicU8, AtomicU32, Ordering},
+};
+
+use crate::bitboard::Bitboard;
+
+/*
+    a table file starts with MAGIC, FORMAT_VERSION (which describes the entry layout),
+    the width, height and connect of the board, BUCKET_SIZE, the key words of an entry
+    and 1 if it has a best move on one byte each, then the number of entries on 8 bytes,
+    followed by the key words (4 bytes each), the values, the played moves and the best
+    moves if any, all numbers in little endian
+*/
+const MAGIC: &[u8; 4] = b"C4TT";
+const FORMAT_VERSION: u8 = 4;
+// everything before the number of entries
+const HEADER_BYTES: usize = 11;
+
+// bytes of an entry besides its key words and its best move
+const ENTRY_BYTES: usize = 2 * size_of::<AtomicU8>();
+// a key can be stored in any entry of its bucket
+const BUCKET_SIZE: usize = 4;
+// enough for the 128 bits of the widest bitboard
+const MAX_KEY_WORDS: usize = 4;
+
+/*
+    only the low 32 bits of a key are stored per key word, the bucket (key % buckets)
+    disambiguates the rest as long as key < 2^(32 * key_words) * buckets
+    (Chinese remainder theorem) for an odd number of buckets, so long keys and small
+    tables take more key words
+*/
+type KeyWord = u32;
+
+/*
+    keys and values are stored in parallel arrays, threads share the table
+    through an Arc without locks (see Engine::with_table), the first key word is mixed
+    with the value and the other key words so that words written by two different
+    threads are not read as an entry
+*/
+pub struct TranspositionTable<B: Bitboard = u64> {
+    // length of the keys which the table tells apart, W * (H + 1) of the board
+    key_bits: usize,
+    key_words: usize,
+    // key_words consecutive words per entry
+    keys: Vec<AtomicU32>,
+    values: Vec<AtomicU8>,
+    // played moves of the positions, those closer to the root cost more to search
+    played_moves: Vec<AtomicU8>,
+    /*
+        column which cut in the position of the stored key (which may be mirrored),
+        the width of the board after a fail-low, only a hint for the move ordering
+        as it is not mixed in the key, empty for the tables without best moves
+    */
+    best_moves: Vec<AtomicU8>,
+    _key: PhantomData<B>,
 }
 
-#[derive(Clone, Copy)]
-struct Entry(u64);
+impl<B: Bitboard> TranspositionTable<B> {
+    /*
+        the size is rounded down to whole buckets, whose number should be prime,
+        key_bits is W * (H + 1) for the keys of a board, best_moves adds a byte
+        per entry for the move ordering hints (see EngineBuilder::best_move_hints)
+    */
+    pub fn new(size: usize, key_bits: usize, best_moves: bool) -> Self {
+        assert!(key_bits <= B::BITS, "the keys do not fit in the bitboard");
+        let size = (size / BUCKET_SIZE).max(1) * BUCKET_SIZE;
+        let key_words = key_words(size / BUCKET_SIZE, key_bits);
+        Self {
+            key_bits,
+            key_words,
+            keys: (0..size * key_words).map(|_| AtomicU32::new(0)).collect(),
+            values: (0..size).map(|_| AtomicU8::new(0)).collect(),
+            played_moves: (0..size).map(|_| AtomicU8::new(0)).collect(),
+            best_moves: (0..size * best_moves as usize)
+                .map(|_| AtomicU8::new(0))
+                .collect(),
+            _key: PhantomData,
+        }
+    }
 
-impl Entry {
-    #[inline]
-    fn key(self) -> u64 {
-        self.0 >> 8
+    // the largest prime number of buckets which fits in memory_mb megabytes with their key words
+    pub fn with_memory(memory_mb: usize, key_bits: usize, best_moves: bool) -> Self {
+        let mut words = 1;
+        loop {
+            let entry_bytes = entry_bytes(words, best_moves);
+            let buckets = prev_prime((memory_mb << 20) / (entry_bytes * BUCKET_SIZE));
+            if key_words(buckets, key_bits) <= words {
+                return Self::new(buckets * BUCKET_SIZE, key_bits, best_moves);
+            }
+            words += 1;
+        }
     }
 
-    #[inline]
-    fn value(self) -> u8 {
-        (self.0 & 0xFF) as u8
+    // number of entries
+    pub fn size(&self) -> usize {
+        self.values.len()
     }
 
-    #[inline]
-    fn new(key: u64, value: u8) -> Self {
-        Entry((key << 8) | value as u64)
+    pub fn key_bits(&self) -> usize {
+        self.key_bits
     }
-}
 
-impl TranspositionTable {
-    pub fn new(size: usize) -> Self {
-        Self { table: vec![Entry(0); size] }
+    pub fn has_best_moves(&self) -> bool {
+        !self.best_moves.is_empty()
     }
 
-    fn index(&self, key: u64) -> usize {
-        key as usize % self.table.len()
+    // the board is only recorded in the header, the engine checks it on load
+    pub(crate) fn save(&self, table_path: &str, board: [usize; 3]) -> Result<(), Error> {
+        let file = File::create(table_path)?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&board.map(|dim| dim as u8))?;
+        writer.write_all(&[
+            BUCKET_SIZE as u8,
+            self.key_words as u8,
+            self.has_best_moves() as u8,
+        ])?;
+        writer.write_all(&(self.size() as u64).to_le_bytes())?;
+
+        for key in &self.keys {
+            writer.write_all(&key.load(Ordering::Relaxed).to_le_bytes())?;
+        }
+        for value in &self.values {
+            writer.write_all(&[value.load(Ordering::Relaxed)])?;
+        }
+        for moves in self.played_moves.iter().chain(&self.best_moves) {
+            writer.write_all(&[moves.load(Ordering::Relaxed)])?;
+        }
+        writer.flush()
     }
 
-    pub fn get(&self, key: u64) -> Option<u8> {
-        let ind = self.index(key);
-        let entry = self.table[ind];
-        if entry.key() == key {
-            Some(entry.value())
-        } else {
-            None
+    pub(crate) fn load(table_path: &str, board: [usize; 3]) -> Result<Self, Error> {
+        let file = File::open(table_path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC || header[4] != FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a transposition table file of this version",
+            ));
+        }
+        if header[5..8] != board.map(|dim| dim as u8) || header[8] as usize != BUCKET_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the transposition table was saved for another board",
+            ));
+        }
+        let mut size_bytes = [0u8; 8];
+        reader.read_exact(&mut size_bytes)?;
+        let size = u64::from_le_bytes(size_bytes);
+        let key_bits = board[0] * (board[1] + 1);
+        let stored_words = header[9] as usize;
+        let best_moves = header[10] == 1;
+        // checked before allocating, the size of a corrupted file could be anything
+        let entry_bytes = entry_bytes(stored_words, best_moves) as u64;
+        let expected_len = size
+            .checked_mul(entry_bytes)
+            .and_then(|len| len.checked_add((HEADER_BYTES + size_of::<u64>()) as u64));
+        if size == 0
+            || header[10] > 1
+            || !size.is_multiple_of(BUCKET_SIZE as u64)
+            || expected_len != Some(file_len)
+            || stored_words != key_words(size as usize / BUCKET_SIZE, key_bits)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "invalid transposition table size",
+            ));
+        }
+
+        let table = Self::new(size as usize, key_bits, best_moves);
+        let mut key_bytes = [0u8; 4];
+        for key in &table.keys {
+            reader.read_exact(&mut key_bytes)?;
+            key.store(u32::from_le_bytes(key_bytes), Ordering::Relaxed);
         }
+        let mut byte = [0u8; 1];
+        for entry in table
+            .values
+            .iter()
+            .chain(&table.played_moves)
+            .chain(&table.best_moves)
+        {
+            reader.read_exact(&mut byte)?;
+            entry.store(byte[0], Ordering::Relaxed);
+        }
+        Ok(table)
     }
 
-    pub fn put(&mut self, key: u64, value: u8) {
-        let ind = self.index(key);
-        let entry = Entry::new(key, value);
-        self.table[ind] = entry;
+    // first entry of the bucket
+    fn index(&self, key: B) -> usize {
+        key.reduce((self.size() / BUCKET_SIZE) as u64) as usize * BUCKET_SIZE
     }
-}
\ No newline at end of file
+
+    // the key words of a key, from the lowest
+    #[inline]
+    fn split(&self, key: B) -> [KeyWord; MAX_KEY_WORDS] {
+        let mut words = [0; MAX_KEY_WORDS];
+        for (ind, word) in words.iter_mut().enumerate().take(self.key_words) {
+            *word = (key >> (ind * KeyWord::BITS as usize)).low_u64() as KeyWord;
+        }
+        words
+    }
+
+    // the first word is checked first as it differs for most positions
+    #[inline]
+    fn matches(&self, ind: usize, words: &[KeyWord], value: u8) -> bool {
+        let first = ind * self.key_words;
+        self.keys[first].load(Ordering::Relaxed) == mix(words[0], value, &words[1..])
+            && (1..self.key_words)
+                .all(|word| self.keys[first + word].load(Ordering::Relaxed) == words[word])
+    }
+
+    // the value and the best move, usize::MAX without best moves
+    pub(crate) fn get(&self, key: B) -> Option<(u8, usize)> {
+        let bucket = self.index(key);
+        let words = self.split(key);
+        let words = &words[..self.key_words];
+        (bucket..bucket + BUCKET_SIZE).find_map(|ind| {
+            let value = self.values[ind].load(Ordering::Relaxed);
+            // empty slots hold value 0, which is never stored
+            (value != 0 && self.matches(ind, words, value)).then(|| {
+                let best_move = self.best_moves.get(ind).map_or(usize::MAX, |best_move| {
+                    best_move.load(Ordering::Relaxed) as usize
+                });
+                (value, best_move)
+            })
+        })
+    }
+
+    /*
+        replaces the entry of the same position if there is one,
+        otherwise an empty entry or the one with the most played moves
+    */
+    pub(crate) fn put(&self, key: B, value: u8, best_move: usize, played_moves: usize) {
+        let bucket = self.index(key);
+        let words = self.split(key);
+        let words = &words[..self.key_words];
+        let mut victim = bucket;
+        let mut victim_moves = 0;
+        for ind in bucket..bucket + BUCKET_SIZE {
+            let stored_value = self.values[ind].load(Ordering::Relaxed);
+            if stored_value == 0 || self.matches(ind, words, stored_value) {
+                victim = ind;
+                break;
+            }
+            let moves = self.played_moves[ind].load(Ordering::Relaxed);
+            if moves > victim_moves {
+                victim = ind;
+                victim_moves = moves;
+            }
+        }
+
+        self.values[victim].store(value, Ordering::Relaxed);
+        self.played_moves[victim].store(played_moves as u8, Ordering::Relaxed);
+        if let Some(stored) = self.best_moves.get(victim) {
+            stored.store(best_move as u8, Ordering::Relaxed);
+        }
+        // the first word last, so that the entry matches once complete
+        let first = victim * self.key_words;
+        for (stored, &word) in self.keys[first + 1..].iter().zip(&words[1..]) {
+            stored.store(word, Ordering::Relaxed);
+        }
+        self.keys[first].store(mix(words[0], value, &words[1..]), Ordering::Relaxed);
+    }
+}
+
+fn entry_bytes(key_words: usize, best_moves: bool) -> usize {
+    key_words * size_of::<KeyWord>() + ENTRY_BYTES + best_moves as usize * size_of::<AtomicU8>()
+}
+
+/*
+    words per entry so that keys of key_bits bits cannot be mistaken for each other,
+    the bucket only tells the high bits apart when it is coprime with 2^32, so an odd
+    number of buckets, otherwise the key words hold the whole key
+*/
+fn key_words(buckets: usize, key_bits: usize) -> usize {
+    let bucket_bits = if !buckets.is_multiple_of(2) {
+        buckets.ilog2() as usize
+    } else {
+        0
+    };
+    key_bits
+        .saturating_sub(bucket_bits)
+        .div_ceil(KeyWord::BITS as usize)
+        .max(1)
+}
+
+// the multiplier is odd so different values and other key words change the first word differently
+#[inline]
+fn mix(word: KeyWord, value: u8, other_words: &[KeyWord]) -> KeyWord {
+    let hash = other_words.iter().fold(
+        (value as KeyWord).wrapping_mul(0x9E37_79B9),
+        |hash, &other| (hash ^ other).wrapping_mul(0x9E37_79B9),
+    );
+    word ^ hash
+}
+
+// a prime size spreads the keys, which are multiples of powers of 2, over all the entries
+fn prev_prime(n: usize) -> usize {
+    (2..=n).rev().find(|&n| is_prime(n)).unwrap_or(1)
+}
+
+fn is_prime(n: usize) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}